@@ -1,51 +1,27 @@
-use ptv::{DeparturesParams, ResponseExpand, SearchParams, Stop};
-use std::cmp::Reverse;
-use std::iter::zip;
-use std::str::FromStr;
+use ptv::{DeparturesParams, ResponseExpand, Stop, StopResolution};
 use time::OffsetDateTime;
 
+#[allow(clippy::upper_case_acronyms)]
 type PTV = ptv::PTV<::reqwest::Client>;
 
-fn similar(left: &str, right: &str) -> usize {
-    let matching = zip(left.chars(), right.chars())
-        .take_while(|(l, r)| l.eq_ignore_ascii_case(r))
-        .count();
-    if matching == right.len() && matching == left.len() {
-        return usize::MAX;
-    } else {
-        matching
-    }
-}
-
 async fn search_for_stop(ptv: &PTV, search: &str) -> Stop {
-    let params = SearchParams {
-        include_outlets: Some(false),
-        match_stop_by_suburb: Some(false),
-        match_route_by_suburb: Some(false),
-        ..Default::default()
-    };
-
-    let mut search_results = ptv
-        .search(&search, params)
+    let resolution = ptv
+        .resolve_stop(search, Default::default())
         .await
         .expect("Failed to search for station");
 
-    search_results
-        .stops
-        .sort_by_key(|stop| Reverse(similar(&search, &stop.stop_name)));
-
-    if search_results.stops.len() > 1 {
-        println!("Multiple stops found for: {search}");
-        for stop in &search_results.stops {
-            println!("    {} {}", stop.stop(), stop.stop_name);
+    match resolution {
+        StopResolution::Literal(stop) => stop,
+        StopResolution::Found(candidate) => candidate.stop.stop(),
+        StopResolution::Ambiguous(candidates) => {
+            println!("Multiple stops found for: {search}");
+            for candidate in &candidates {
+                println!("    {} {}", candidate.stop.stop(), candidate.stop.stop_name);
+            }
+            candidates[0].stop.stop()
         }
+        StopResolution::NotFound => panic!("Station search returned nothing"),
     }
-
-    search_results
-        .stops
-        .first()
-        .expect("Station search returned nothing")
-        .stop()
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -62,11 +38,7 @@ async fn main() {
     // Create the API client instance
     let ptv = PTV::new(devid, key.to_string(), "RustPTVExample/0.1");
 
-    let stop = if let Ok(stop) = Stop::from_str(&search) {
-        stop
-    } else {
-        search_for_stop(&ptv, &search).await
-    };
+    let stop = search_for_stop(&ptv, &search).await;
 
     // Setup parameters for our departures query
     // We ask for five results per route, and extra details for related routes, runs, and vehicles
@@ -99,34 +71,27 @@ async fn main() {
 
     for route in result.routes.values() {
         println!("Route: {}", route.route_name);
-        for departure in &result.departures {
+        for departure in result.resolved() {
             if departure.route_id != route.route_id {
                 continue;
             }
-            let scheduled = departure.scheduled_departure_utc;
-            let estimated = departure.estimated_departure_utc.unwrap_or(scheduled);
-            let departing_min = (scheduled - now).whole_minutes();
+            let departing_min = (departure.scheduled_departure_utc - now).whole_minutes();
             if departing_min > 90 {
                 continue;
             }
 
-            let platform = departure
-                .platform_number
-                .clone()
-                .unwrap_or_else(|| "??".to_string());
+            let platform = departure.platform().unwrap_or("??");
 
-            let delayed = (estimated - scheduled).whole_minutes();
+            let delayed = departure.delay().unwrap_or_default().whole_minutes();
             let delayed = if delayed > 0 {
                 format!(" (Delayed {delayed}Min)")
             } else {
                 String::new()
             };
 
-            let run = &result.runs.get(&departure.run_ref).unwrap();
-
-            let details = if let Some(description) = run
-                .vehicle_descriptor
-                .as_ref()
+            let details = if let Some(description) = departure
+                .run()
+                .and_then(|run| run.vehicle_descriptor.as_ref())
                 .and_then(|x| x.description.as_ref())
             {
                 format!(" [{description}]")
@@ -134,7 +99,7 @@ async fn main() {
                 String::new()
             };
 
-            let destination = &run.destination_name;
+            let destination = departure.destination().unwrap_or("Unknown");
 
             println!("    Platform {platform:-2} departing in {departing_min:-2}Min to {destination}{delayed}{details}");
         }
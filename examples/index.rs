@@ -0,0 +1,93 @@
+use ptv::{LatLon, NetworkIndex, StopResolution};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+#[allow(clippy::upper_case_acronyms)]
+type PTV = ptv::PTV<::reqwest::Client>;
+
+const USAGE: &str = "Usage:
+    index build <index.json>
+    index stop <index.json> <name or Stop:Type/id>
+    index route <index.json> <name or number>
+    index near <index.json> <latitude> <longitude> [metres]";
+
+fn load(path: &str) -> NetworkIndex {
+    let file = File::open(path).expect("Failed to open index");
+    NetworkIndex::read(BufReader::new(file)).expect("Failed to read index")
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["build", path] => {
+            // Only building needs the API
+            let devid = std::env::var("PTV_DEVID").expect("PTV_DEVID not set");
+            let key = std::env::var("PTV_KEY").expect("PTV_KEY not set");
+            let ptv = PTV::new(devid, key, "RustPTVIndex/0.1");
+            let index = ptv.build_index().await.expect("Failed to build index");
+            let file = File::create(path).expect("Failed to create index");
+            index
+                .write(BufWriter::new(file))
+                .expect("Failed to write index");
+            println!(
+                "Indexed {} routes and {} stops",
+                index.routes.len(),
+                index.stops.len()
+            );
+        }
+        ["stop", path, text] => {
+            let index = load(path);
+            let candidates = match index.search_stops(text, &Default::default()) {
+                StopResolution::Literal(stop) => index
+                    .stop(&stop)
+                    .map(|s| s.stop.clone())
+                    .into_iter()
+                    .collect(),
+                StopResolution::Found(candidate) => vec![candidate.stop],
+                StopResolution::Ambiguous(candidates) => {
+                    candidates.into_iter().map(|c| c.stop).collect()
+                }
+                StopResolution::NotFound => Vec::new(),
+            };
+            for stop in candidates {
+                println!("{} {} ({})", stop.stop(), stop.stop_name, stop.stop_suburb);
+            }
+        }
+        ["route", path, text] => {
+            let index = load(path);
+            for route in index.search_routes(text) {
+                println!(
+                    "{} {} {} ({} stops)",
+                    route.route.route(),
+                    route.route.route_number,
+                    route.route.route_name,
+                    route.stops.len()
+                );
+            }
+        }
+        ["near", path, latitude, longitude, rest @ ..] => {
+            let index = load(path);
+            let location = LatLon::new(
+                latitude.parse().expect("Invalid latitude"),
+                longitude.parse().expect("Invalid longitude"),
+            );
+            let radius = rest
+                .first()
+                .map_or(500.0, |r| r.parse().expect("Invalid radius"));
+            for (stop, distance) in index.nearest_stops(location, radius, 20) {
+                println!(
+                    "{distance:6.0}m {} {}",
+                    stop.stop.stop(),
+                    stop.stop.stop_name
+                );
+            }
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    }
+}
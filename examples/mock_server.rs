@@ -0,0 +1,26 @@
+use ptv::{Cassette, MockServer};
+use std::sync::Arc;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    // Serve a cassette recorded with `RecordingClient`, clients must sign requests with these credentials
+    let devid = std::env::var("PTV_DEVID").unwrap_or_else(|_| "0".to_string());
+    let key = std::env::var("PTV_KEY")
+        .unwrap_or_else(|_| "00000000-0000-0000-0000-000000000000".to_string());
+    let mut args = std::env::args().skip(1);
+    let cassette = args
+        .next()
+        .expect("Usage: mock_server <cassette.json> [address]");
+    let address = args.next().unwrap_or_else(|| "127.0.0.1:8081".to_string());
+
+    let cassette = Cassette::load(&cassette).expect("Failed to load cassette");
+    let server = Arc::new(MockServer::new(devid, key).with_cassette(&cassette));
+
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .expect("Failed to listen");
+    println!("Listening on http://{address}/v3/...");
+    axum::serve(listener, server.router())
+        .await
+        .expect("Failed to serve");
+}
@@ -0,0 +1,36 @@
+use ptv::{ProxyParams, RateLimit, SigningProxy};
+use std::time::Duration;
+
+#[allow(clippy::upper_case_acronyms)]
+type PTV = ptv::PTV<::reqwest::Client>;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    // The developer ID and Key stay on the server, clients make unsigned requests
+    let devid = std::env::var("PTV_DEVID").expect("PTV_DEVID not set");
+    let key = std::env::var("PTV_KEY").expect("PTV_KEY not set");
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+
+    let ptv = PTV::new(devid, key, "RustPTVProxy/0.1");
+
+    // Cache responses for 30 seconds, and forward at most 10 requests a second upstream
+    let params = ProxyParams {
+        cache_ttl: Some(Duration::from_secs(30)),
+        rate_limit: Some(RateLimit {
+            requests: 10,
+            per: Duration::from_secs(1),
+        }),
+        ..Default::default()
+    };
+    let router = SigningProxy::new(ptv, params).router();
+
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .expect("Failed to listen");
+    println!("Listening on http://{address}/v3/...");
+    axum::serve(listener, router)
+        .await
+        .expect("Failed to serve");
+}
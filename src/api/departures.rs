@@ -1,9 +1,12 @@
 use crate::*;
+use futures_util::StreamExt;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::collections::BTreeSet;
 
 /// Response from [PTV::departures]
 ///
 /// Swagger type: `V3.DeparturesResponse`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeparturesResponse {
     /// Timetabled and real-time service departures
     pub departures: Vec<DepartureDetails>,
@@ -19,6 +22,150 @@ pub struct DeparturesResponse {
     pub disruptions: BTreeMap<DisruptionId, DisruptionDetails>,
     /// API status / Metadata
     pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(
+    DeparturesResponse,
+    departures,
+    stops,
+    routes,
+    runs,
+    directions,
+    disruptions,
+    status
+);
+api_response!(DeparturesResponse);
+
+impl DeparturesResponse {
+    /// Merge another response into this one
+    ///
+    /// Departures are appended (skipping ones already present), the maps of related details are
+    /// combined, and the status is taken from `other`.
+    ///
+    /// Details already present are kept. Stop identifiers are only unique within a route type,
+    /// so merging departures from stops of different route types with the same identifier
+    /// (i.e. `Stop:Train/1181` and `Stop:Vline/1181`) keeps the details of the first stop only.
+    pub fn merge(&mut self, other: DeparturesResponse) {
+        let mut seen: BTreeSet<_> = self.departures.iter().map(departure_key).collect();
+        for departure in other.departures {
+            if seen.insert(departure_key(&departure)) {
+                self.departures.push(departure);
+            }
+        }
+        merge_map(&mut self.stops, other.stops);
+        merge_map(&mut self.routes, other.routes);
+        merge_map(&mut self.runs, other.runs);
+        merge_map(&mut self.directions, other.directions);
+        merge_map(&mut self.disruptions, other.disruptions);
+        merge_map(&mut self.extra, other.extra);
+        self.status = other.status;
+    }
+    /// Departures joined with their related stops, routes, runs, directions and disruptions
+    pub fn resolved(&self) -> impl Iterator<Item = Departure<'_>> {
+        self.departures.iter().map(move |details| Departure {
+            response: self,
+            details,
+        })
+    }
+}
+
+/// Internal helper to identify a departure, a run departing a stop
+fn departure_key(departure: &DepartureDetails) -> (RunRef, StopId, i32) {
+    (
+        departure.run_ref.clone(),
+        departure.stop_id.clone(),
+        departure.departure_sequence,
+    )
+}
+
+/// Internal helper to add entries which aren't already in a map
+fn merge_map<K: Ord, V>(map: &mut BTreeMap<K, V>, other: BTreeMap<K, V>) {
+    for (key, value) in other {
+        map.entry(key).or_insert(value);
+    }
+}
+
+/// Response from [PTV::departures_many]
+#[derive(Clone, Debug)]
+pub struct ManyDeparturesResponse {
+    /// Departures from every stop that succeeded, merged into a single response ordered by
+    /// scheduled departure time. `None` if every stop failed.
+    pub response: Option<DeparturesResponse>,
+    /// Stops that failed, with the reason
+    pub errors: Vec<(Stop, Error)>,
+}
+
+/// A departure from a [DeparturesResponse], with accessors for its related details
+///
+/// Related details are only available when they were expanded in the request
+/// (see [DeparturesParams::expand]), otherwise the accessors return `None`.
+#[derive(Clone, Copy)]
+pub struct Departure<'a> {
+    response: &'a DeparturesResponse,
+    details: &'a DepartureDetails,
+}
+impl<'a> Departure<'a> {
+    /// The underlying departure details
+    pub fn details(&self) -> &'a DepartureDetails {
+        self.details
+    }
+    /// The stop being departed from
+    pub fn stop(&self) -> Option<&'a StopDetails> {
+        self.response.stops.get(&self.details.stop_id)
+    }
+    /// The route of the departing service
+    pub fn route(&self) -> Option<&'a RouteDetails> {
+        self.response.routes.get(&self.details.route_id)
+    }
+    /// The trip/service run that's departing
+    pub fn run(&self) -> Option<&'a RunDetails> {
+        self.response.runs.get(&self.details.run_ref)
+    }
+    /// The direction of travel
+    pub fn direction(&self) -> Option<&'a DirectionDetails> {
+        self.response.directions.get(&self.details.direction_id)
+    }
+    /// Name of the destination of the run
+    pub fn destination(&self) -> Option<&'a str> {
+        self.run().map(|run| run.destination_name.as_str())
+    }
+    /// Disruptions affecting the departure (ones that weren't expanded are skipped)
+    pub fn disruptions(&self) -> impl Iterator<Item = &'a DisruptionDetails> {
+        let disruptions = &self.response.disruptions;
+        self.details
+            .disruption_ids
+            .iter()
+            .filter_map(move |id| disruptions.get(id))
+    }
+    /// Best known departure time, the real-time estimate if there is one, otherwise the scheduled time
+    pub fn departure_time(&self) -> OffsetDateTime {
+        self.details
+            .estimated_departure_utc
+            .unwrap_or(self.details.scheduled_departure_utc)
+    }
+    /// How far the real-time estimate is behind the scheduled time (negative if early)
+    pub fn delay(&self) -> Option<time::Duration> {
+        let estimated = self.details.estimated_departure_utc?;
+        Some(estimated - self.details.scheduled_departure_utc)
+    }
+    /// Platform number at the stop (metropolitan train only)
+    pub fn platform(&self) -> Option<&'a str> {
+        self.details.platform_number.as_deref()
+    }
+}
+impl std::ops::Deref for Departure<'_> {
+    type Target = DepartureDetails;
+    fn deref(&self) -> &Self::Target {
+        self.details
+    }
+}
+impl std::fmt::Debug for Departure<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("Departure").field(self.details).finish()
+    }
 }
 
 /// Values to expand the output of [PTV::departures]
@@ -75,7 +222,76 @@ impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
             route_type = stop.route_type.value(),
             stop_id = stop.id.value(),
         );
-        let url = self.build_url(&path, &params);
-        self.http_client.api_get(url).await
+        let url = self.build_url(&path, &params).await?;
+        self.get("Departures_GetForStop", url).await
+    }
+
+    /// View departures for all routes from a stop, identified by its GTFS stop identifier
+    ///
+    /// Swagger operation: `Departures_GetForStop` (with `gtfs`)
+    pub async fn departures_gtfs(
+        &self,
+        route_type: RouteType,
+        stop_id: &GtfsStopId,
+        mut params: DeparturesParams,
+    ) -> Result<DeparturesResponse> {
+        params.gtfs = Some(true);
+        let path = format!(
+            "/v3/departures/route_type/{route_type}/stop/{stop_id}",
+            route_type = route_type.value(),
+            stop_id = utf8_percent_encode(stop_id.value(), NON_ALPHANUMERIC),
+        );
+        let url = self.build_url(&path, &params).await?;
+        self.get("Departures_GetForStop", url).await
+    }
+
+    /// View departures for all routes from several stops, merged into one response
+    ///
+    /// Up to `concurrency` requests are made at a time. A stop failing doesn't fail the
+    /// whole batch, failures are reported per stop in [ManyDeparturesResponse::errors].
+    pub async fn departures_many(
+        &self,
+        stops: &[Stop],
+        params: DeparturesParams,
+        concurrency: usize,
+    ) -> ManyDeparturesResponse {
+        let results = self.departures_each(stops, params, concurrency).await;
+        let mut merged: Option<DeparturesResponse> = None;
+        let mut errors = Vec::new();
+        for (stop, result) in results {
+            match (result, &mut merged) {
+                (Ok(response), Some(merged)) => merged.merge(response),
+                (Ok(response), None) => merged = Some(response),
+                (Err(error), _) => errors.push((stop.clone(), error)),
+            }
+        }
+        if let Some(merged) = &mut merged {
+            merged
+                .departures
+                .sort_by_key(|departure| departure.scheduled_departure_utc);
+        }
+        ManyDeparturesResponse {
+            response: merged,
+            errors,
+        }
+    }
+
+    /// Internal helper to fetch departures from several stops, up to `concurrency` at a time
+    ///
+    /// Results are in the same order as `stops`, each paired with its stop.
+    pub(crate) async fn departures_each<'a>(
+        &self,
+        stops: &'a [Stop],
+        params: DeparturesParams,
+        concurrency: usize,
+    ) -> Vec<(&'a Stop, Result<DeparturesResponse>)> {
+        futures_util::stream::iter(stops)
+            .map(|stop| {
+                let params = params.clone();
+                async move { (stop, self.departures(stop, params).await) }
+            })
+            .buffered(concurrency.max(1))
+            .collect()
+            .await
     }
 }
@@ -0,0 +1,29 @@
+use crate::*;
+
+/// Response from [PTV::directions_for_route]
+///
+/// Swagger type: `V3.DirectionsResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DirectionsResponse {
+    /// Directions of travel of route
+    pub directions: Vec<DirectionDetails>,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(DirectionsResponse, directions, status);
+api_response!(DirectionsResponse);
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View directions that a route travels in
+    ///
+    /// Swagger operation: `Directions_ForRoute`
+    pub async fn directions_for_route(&self, route_id: &RouteId) -> Result<DirectionsResponse> {
+        let path = format!("/v3/directions/route/{route_id}");
+        let url = self.build_url(&path, ()).await?;
+        self.get("Directions_ForRoute", url).await
+    }
+}
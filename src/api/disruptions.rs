@@ -0,0 +1,192 @@
+use crate::*;
+
+/// Disruptions, grouped by transport mode
+///
+/// Swagger type: `V3.Disruptions`
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Disruptions {
+    /// Subset of disruption information applicable to multiple route_types
+    #[serde(default)]
+    pub general: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to metropolitan train
+    #[serde(default)]
+    pub metro_train: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to metropolitan tram
+    #[serde(default)]
+    pub metro_tram: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to metropolitan bus
+    #[serde(default)]
+    pub metro_bus: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to V/Line train
+    #[serde(default)]
+    pub regional_train: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to V/Line coach
+    #[serde(default)]
+    pub regional_coach: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to regional bus
+    #[serde(default)]
+    pub regional_bus: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to school bus
+    #[serde(default)]
+    pub school_bus: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to telebus services
+    #[serde(default)]
+    pub telebus: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to night bus
+    #[serde(default)]
+    pub night_bus: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to ferry
+    #[serde(default)]
+    pub ferry: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to interstate train
+    #[serde(default)]
+    pub interstate_train: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to skybus
+    #[serde(default)]
+    pub skybus: Vec<DisruptionDetails>,
+    /// Subset of disruption information applicable to taxi
+    #[serde(default)]
+    pub taxi: Vec<DisruptionDetails>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+impl Disruptions {
+    /// All disruptions, regardless of transport mode
+    pub fn iter(&self) -> impl Iterator<Item = &DisruptionDetails> {
+        [
+            &self.general,
+            &self.metro_train,
+            &self.metro_tram,
+            &self.metro_bus,
+            &self.regional_train,
+            &self.regional_coach,
+            &self.regional_bus,
+            &self.school_bus,
+            &self.telebus,
+            &self.night_bus,
+            &self.ferry,
+            &self.interstate_train,
+            &self.skybus,
+            &self.taxi,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+unknown_fields!(
+    Disruptions,
+    general,
+    metro_train,
+    metro_tram,
+    metro_bus,
+    regional_train,
+    regional_coach,
+    regional_bus,
+    school_bus,
+    telebus,
+    night_bus,
+    ferry,
+    interstate_train,
+    skybus,
+    taxi
+);
+
+/// Response from [PTV::disruptions], [PTV::disruptions_for_route] and [PTV::disruptions_for_stop]
+///
+/// Swagger type: `V3.DisruptionsResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DisruptionsResponse {
+    /// Disruption information applicable to relevant routes or stops
+    pub disruptions: Disruptions,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(DisruptionsResponse, disruptions, status);
+api_response!(DisruptionsResponse);
+
+/// Response from [PTV::disruption]
+///
+/// Swagger type: `V3.DisruptionResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DisruptionResponse {
+    /// Disruption information applicable to relevant routes or stops
+    pub disruption: DisruptionDetails,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(DisruptionResponse, disruption, status);
+api_response!(DisruptionResponse);
+
+/// Parameters for [PTV::disruptions], [PTV::disruptions_for_route] and [PTV::disruptions_for_stop]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DisruptionsParams {
+    /// Filter by route_type; values returned via RouteTypes API (only used by [PTV::disruptions])
+    pub route_types: Option<Vec<RouteType>>,
+    /// Filter by disruption_mode; values returned via v3/disruptions/modes API (only used by [PTV::disruptions])
+    pub disruption_modes: Option<Vec<i32>>,
+    /// Filter by status of disruption, "current" or "planned"
+    pub disruption_status: Option<String>,
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View all disruptions for all route types
+    ///
+    /// Swagger operation: `Disruptions_GetAllDisruptions`
+    pub async fn disruptions(&self, params: DisruptionsParams) -> Result<DisruptionsResponse> {
+        let url = self.build_url("/v3/disruptions", &params).await?;
+        self.get("Disruptions_GetAllDisruptions", url).await
+    }
+
+    /// View all disruptions for a particular route
+    ///
+    /// Swagger operation: `Disruptions_GetDisruptionsByRoute`
+    pub async fn disruptions_for_route(
+        &self,
+        route_id: &RouteId,
+        params: DisruptionsParams,
+    ) -> Result<DisruptionsResponse> {
+        let path = format!("/v3/disruptions/route/{route_id}");
+        let params = DisruptionsParams {
+            disruption_status: params.disruption_status,
+            ..Default::default()
+        };
+        let url = self.build_url(&path, &params).await?;
+        self.get("Disruptions_GetDisruptionsByRoute", url).await
+    }
+
+    /// View all disruptions for a particular stop
+    ///
+    /// Swagger operation: `Disruptions_GetDisruptionsByStop`
+    pub async fn disruptions_for_stop(
+        &self,
+        stop_id: &StopId,
+        params: DisruptionsParams,
+    ) -> Result<DisruptionsResponse> {
+        let path = format!("/v3/disruptions/stop/{stop_id}");
+        let params = DisruptionsParams {
+            disruption_status: params.disruption_status,
+            ..Default::default()
+        };
+        let url = self.build_url(&path, &params).await?;
+        self.get("Disruptions_GetDisruptionsByStop", url).await
+    }
+
+    /// View a specific disruption
+    ///
+    /// Swagger operation: `Disruptions_GetDisruptionById`
+    pub async fn disruption(&self, disruption_id: &DisruptionId) -> Result<DisruptionResponse> {
+        let path = format!("/v3/disruptions/{disruption_id}");
+        let url = self.build_url(&path, ()).await?;
+        self.get("Disruptions_GetDisruptionById", url).await
+    }
+}
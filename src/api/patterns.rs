@@ -0,0 +1,70 @@
+use crate::*;
+
+/// Response from [PTV::pattern]
+///
+/// Swagger type: `V3.StopPatternResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PatternResponse {
+    /// Disruption information applicable to relevant routes or stops
+    pub disruptions: Vec<DisruptionDetails>,
+    /// Timetabled and real-time service departures, one for each stop of the run
+    pub departures: Vec<DepartureDetails>,
+    /// A train station, tram stop, bus stop, regional coach stop or Night Bus stop
+    pub stops: BTreeMap<StopId, StopDetails>,
+    /// Train lines, tram routes, bus routes, regional coach routes, Night Bus routes
+    pub routes: BTreeMap<RouteId, RouteDetails>,
+    /// Individual trips/services of a route
+    pub runs: BTreeMap<RunRef, RunDetails>,
+    /// Directions of travel of route
+    pub directions: BTreeMap<DirectionId, DirectionDetails>,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(
+    PatternResponse,
+    disruptions,
+    departures,
+    stops,
+    routes,
+    runs,
+    directions,
+    status
+);
+api_response!(PatternResponse);
+
+/// Parameters for [PTV::pattern]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PatternParams {
+    /// List of objects to be returned in full (i.e. expanded)
+    ///
+    /// options include: All, Stop, Route, Run, Direction, Disruption, VehiclePosition, VehicleDescriptor or None.
+    /// Run must be expanded to receive VehiclePosition and VehicleDescriptor information.
+    pub expand: Option<Vec<ResponseExpand>>,
+    /// Filter by stop_id; values returned by Stops API
+    pub stop_id: Option<StopId>,
+    /// Filter by the date and time of the request (ISO 8601 UTC format)
+    pub date_utc: Option<String>,
+    /// Include any skipped stops in a stopping pattern (default = false)
+    pub include_skipped_stops: Option<bool>,
+    /// Indicates if geopath data will be returned (default = false)
+    pub include_geopath: Option<bool>,
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View the stopping pattern for a specific trip/service run
+    ///
+    /// Swagger operation: `Patterns_GetPatternByRun`
+    pub async fn pattern(&self, run: &Run, params: PatternParams) -> Result<PatternResponse> {
+        let path = format!(
+            "/v3/pattern/run/{run_ref}/route_type/{route_type}",
+            run_ref = run.id.value(),
+            route_type = run.route_type.value(),
+        );
+        let url = self.build_url(&path, &params).await?;
+        self.get("Patterns_GetPatternByRun", url).await
+    }
+}
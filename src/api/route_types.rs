@@ -0,0 +1,28 @@
+use crate::*;
+
+/// Response from [PTV::route_types]
+///
+/// Swagger type: `V3.RouteTypesResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RouteTypesResponse {
+    /// Transport mode identifiers
+    pub route_types: Vec<RouteTypeDetails>,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(RouteTypesResponse, route_types, status);
+api_response!(RouteTypesResponse);
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View all route types and their names
+    ///
+    /// Swagger operation: `RouteTypes_GetRouteTypes`
+    pub async fn route_types(&self) -> Result<RouteTypesResponse> {
+        let url = self.build_url("/v3/route_types", ()).await?;
+        self.get("RouteTypes_GetRouteTypes", url).await
+    }
+}
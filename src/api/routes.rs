@@ -0,0 +1,37 @@
+use crate::*;
+
+/// Response from [PTV::routes]
+///
+/// Swagger type: `V3.RouteResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RoutesResponse {
+    /// Train lines, tram routes, bus routes, regional coach routes, Night Bus routes
+    pub routes: Vec<RouteDetails>,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(RoutesResponse, routes, status);
+api_response!(RoutesResponse);
+
+/// Parameters for [PTV::routes]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RoutesParams {
+    /// Filter by route_type; values returned via RouteTypes API
+    pub route_types: Option<Vec<RouteType>>,
+    /// Filter by name of route (accepts partial route name matches)
+    pub route_name: Option<String>,
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View route names and numbers for all routes
+    ///
+    /// Swagger operation: `Routes_OneOrMoreRoutes`
+    pub async fn routes(&self, params: RoutesParams) -> Result<RoutesResponse> {
+        let url = self.build_url("/v3/routes", &params).await?;
+        self.get("Routes_OneOrMoreRoutes", url).await
+    }
+}
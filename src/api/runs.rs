@@ -0,0 +1,73 @@
+use crate::*;
+
+/// Response from [PTV::runs_for_route]
+///
+/// Swagger type: `V3.RunsResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RunsResponse {
+    /// Individual trips/services of a route
+    pub runs: Vec<RunDetails>,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(RunsResponse, runs, status);
+api_response!(RunsResponse);
+
+/// Response from [PTV::run]
+///
+/// Swagger type: `V3.RunResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RunResponse {
+    /// Individual trip/service run
+    pub run: RunDetails,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(RunResponse, run, status);
+api_response!(RunResponse);
+
+/// Parameters for [PTV::runs_for_route] and [PTV::run]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RunsParams {
+    /// List of objects to be returned in full (i.e. expanded)
+    ///
+    /// options include: All, VehiclePosition, VehicleDescriptor, or None.
+    pub expand: Option<Vec<ResponseExpand>>,
+    /// Filter by the date and time of the request (ISO 8601 UTC format)
+    pub date_utc: Option<String>,
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View all trip/service runs for a specific route
+    ///
+    /// Swagger operation: `Runs_ForRouteAndRouteType`
+    pub async fn runs_for_route(&self, route: &Route, params: RunsParams) -> Result<RunsResponse> {
+        let path = format!(
+            "/v3/runs/route/{route_id}/route_type/{route_type}",
+            route_id = route.id,
+            route_type = route.route_type.value(),
+        );
+        let url = self.build_url(&path, &params).await?;
+        self.get("Runs_ForRouteAndRouteType", url).await
+    }
+    /// View the trip/service run for a specific run reference and route type
+    ///
+    /// Swagger operation: `Runs_ForRunAndRouteType`
+    pub async fn run(&self, run: &Run, params: RunsParams) -> Result<RunResponse> {
+        let path = format!(
+            "/v3/runs/{run_ref}/route_type/{route_type}",
+            run_ref = run.id.value(),
+            route_type = run.route_type.value(),
+        );
+        let url = self.build_url(&path, &params).await?;
+        self.get("Runs_ForRunAndRouteType", url).await
+    }
+}
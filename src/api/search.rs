@@ -4,7 +4,7 @@ use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 /// Response from [PTV::search]
 ///
 /// Swagger type: `V3.SearchResult`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SearchResponse {
     /// Train stations, tram stops, bus stops, regional coach stops or Night Bus stops
     pub stops: Vec<StopDetails>,
@@ -14,19 +14,25 @@ pub struct SearchResponse {
     pub outlets: Vec<OutletDetails>,
     /// API status / Metadata
     pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
+unknown_fields!(SearchResponse, stops, routes, outlets, status);
+api_response!(SearchResponse);
+
 /// Parameters for [PTV::search]
 #[derive(Clone, Debug, Default, Serialize)]
 pub struct SearchParams {
     /// Filter by route_type; values returned via RouteTypes API (note: stops and routes are ordered by route_types specified)
     pub route_types: Option<Vec<RouteType>>,
     /// Filter by geographic coordinate of latitude
-    pub latitude: Option<f32>,
+    pub latitude: Option<f64>,
     /// Filter by geographic coordinate of longitude
-    pub longitude: Option<f32>,
+    pub longitude: Option<f64>,
     /// Filter by maximum distance (in metres) from location specified via latitude and longitude parameters
-    pub max_distance: Option<f32>,
+    pub max_distance: Option<f64>,
     /// Placeholder for future development; currently unavailable
     pub include_addresses: Option<bool>,
     /// Indicates if outlets will be returned in response (default = true)
@@ -39,6 +45,20 @@ pub struct SearchParams {
     pub match_stop_by_gtfs_stop_id: Option<bool>,
 }
 
+impl SearchParams {
+    /// Filter by distance (in metres) from a location
+    pub fn with_location(mut self, location: LatLon, max_distance: Option<f64>) -> Self {
+        self.latitude = Some(location.latitude);
+        self.longitude = Some(location.longitude);
+        self.max_distance = max_distance;
+        self
+    }
+    /// The location being filtered by, if both latitude and longitude are set
+    pub fn location(&self) -> Option<LatLon> {
+        Some(LatLon::new(self.latitude?, self.longitude?))
+    }
+}
+
 impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
     /// View stops, routes and myki ticket outlets that match the search term
     ///
@@ -50,7 +70,19 @@ impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
         let search_term = utf8_percent_encode(search_term, NON_ALPHANUMERIC).to_string();
         let search_term = utf8_percent_encode(&search_term, NON_ALPHANUMERIC);
         let path = format!("/v3/search/{search_term}");
-        let url = self.build_url(&path, &params);
-        self.http_client.api_get(url).await
+        let url = self.build_url(&path, &params).await?;
+        self.get("Search_Search", url).await
+    }
+
+    /// View stops matching a GTFS stop identifier
+    ///
+    /// Swagger operation: `Search_Search` (with `match_stop_by_gtfs_stop_id`)
+    pub async fn search_gtfs_stop(
+        &self,
+        stop_id: &GtfsStopId,
+        mut params: SearchParams,
+    ) -> Result<SearchResponse> {
+        params.match_stop_by_gtfs_stop_id = Some(true);
+        self.search(stop_id.value(), params).await
     }
 }
@@ -0,0 +1,105 @@
+use crate::*;
+
+/// Response from [PTV::stops_near]
+///
+/// Swagger type: `V3.StopsByDistanceResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StopsByDistanceResponse {
+    /// Train stations, tram stops, bus stops, regional coach stops or Night Bus stops
+    pub stops: Vec<StopDetails>,
+    /// Disruption information applicable to relevant routes or stops
+    pub disruptions: BTreeMap<DisruptionId, DisruptionDetails>,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(StopsByDistanceResponse, stops, disruptions, status);
+api_response!(StopsByDistanceResponse);
+
+/// Parameters for [PTV::stops_near]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StopsByDistanceParams {
+    /// Filter by route_type; values returned via RouteTypes API
+    pub route_types: Option<Vec<RouteType>>,
+    /// Maximum number of results returned (default = 30)
+    pub max_results: Option<i32>,
+    /// Filter by maximum distance (in metres) from location specified via latitude and longitude parameters (default = 300)
+    pub max_distance: Option<f64>,
+    /// Indicates if stop disruption information will be returned (default = false)
+    pub stop_disruptions: Option<bool>,
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View all stops near a specific location
+    ///
+    /// Swagger operation: `Stops_StopsByGeolocation`
+    pub async fn stops_near(
+        &self,
+        location: LatLon,
+        params: StopsByDistanceParams,
+    ) -> Result<StopsByDistanceResponse> {
+        let path = format!(
+            "/v3/stops/location/{latitude},{longitude}",
+            latitude = location.latitude,
+            longitude = location.longitude,
+        );
+        let url = self.build_url(&path, &params).await?;
+        self.get("Stops_StopsByGeolocation", url).await
+    }
+}
+
+/// Response from [PTV::stops_on_route]
+///
+/// Swagger type: `V3.StopsOnRouteResponse`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StopsOnRouteResponse {
+    /// Train stations, tram stops, bus stops, regional coach stops or Night Bus stops
+    pub stops: Vec<StopDetails>,
+    /// Disruption information applicable to relevant routes or stops
+    pub disruptions: BTreeMap<DisruptionId, DisruptionDetails>,
+    /// Geographic paths of the route (requires `include_geopath`)
+    pub geopath: Option<Vec<GeoPath>>,
+    /// API status / Metadata
+    pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(StopsOnRouteResponse, stops, disruptions, geopath, status);
+api_response!(StopsOnRouteResponse);
+
+/// Parameters for [PTV::stops_on_route]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StopsOnRouteParams {
+    /// An optional direction; values returned by Directions API. When this is set, stop sequence information is returned in the response.
+    pub direction_id: Option<DirectionId>,
+    /// Indicates if stop disruption information will be returned (default = false)
+    pub stop_disruptions: Option<bool>,
+    /// Indicates if geopath data will be returned (default = false)
+    pub include_geopath: Option<bool>,
+    /// Filter geopaths by date (ISO 8601 UTC format) (default = current date)
+    pub geopath_utc: Option<String>,
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View all stops on a specific route
+    ///
+    /// Swagger operation: `Stops_StopsForRoute`
+    pub async fn stops_on_route(
+        &self,
+        route: &Route,
+        params: StopsOnRouteParams,
+    ) -> Result<StopsOnRouteResponse> {
+        let path = format!(
+            "/v3/stops/route/{route_id}/route_type/{route_type}",
+            route_id = route.id,
+            route_type = route.route_type.value(),
+        );
+        let url = self.build_url(&path, &params).await?;
+        self.get("Stops_StopsForRoute", url).await
+    }
+}
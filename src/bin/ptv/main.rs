@@ -0,0 +1,544 @@
+//! Command line interface to the PTV Timetable API
+//!
+//! Credentials come from the `PTV_DEVID` and `PTV_KEY` environment variables, or a TOML config
+//! file containing `devid` and `key` (by default `~/.config/ptv/config.toml`).
+
+use clap::{Parser, Subcommand, ValueEnum};
+use ptv::{
+    melbourne_time, DepartureDetails, DeparturesParams, DeparturesResponse, DisruptionDetails,
+    DisruptionsParams, DisruptionsResponse, PatternParams, PatternResponse, ResolveParams,
+    ResponseExpand, Route, RouteDetails, RouteType, RoutesParams, RoutesResponse, Run, RunDetails,
+    RunResponse, RunsParams, SearchParams, SearchResponse, Stop, StopDetails, StopResolution,
+    StopsByDistanceParams, StopsByDistanceResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use time::format_description::well_known::Iso8601;
+use time::OffsetDateTime;
+
+#[allow(clippy::upper_case_acronyms)]
+type PTV = ptv::PTV<::reqwest::Client>;
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Parser)]
+#[command(
+    name = "ptv",
+    version,
+    about = "Query the Public Transport Victoria Timetable API"
+)]
+struct Cli {
+    /// Output format
+    #[arg(long, short, value_enum, default_value_t = Format::Table, global = true)]
+    format: Format,
+    /// Config file containing `devid` and `key`
+    #[arg(long, env = "PTV_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Aligned columns
+    Table,
+    /// A JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Search for stops and routes
+    Search {
+        /// Text to search for
+        text: String,
+        /// Only include these route types (i.e. train,tram)
+        #[arg(long = "route-type", short = 't', value_delimiter = ',')]
+        route_types: Vec<RouteType>,
+    },
+    /// Upcoming departures from a stop
+    Departures {
+        /// Stop name, or identifier (i.e. `Stop:Train/1071`)
+        stop: String,
+        /// Maximum number of departures per route
+        #[arg(long, short = 'n', default_value_t = 5)]
+        max_results: i32,
+        /// Only include these platforms
+        #[arg(long = "platform", value_delimiter = ',')]
+        platforms: Vec<i32>,
+    },
+    /// Disruptions for route types, a route, or a stop
+    Disruptions {
+        /// Only include these route types (i.e. train,tram)
+        #[arg(long = "route-type", short = 't', value_delimiter = ',')]
+        route_types: Vec<RouteType>,
+        /// Only disruptions for a route (i.e. `Route:Train/14`)
+        #[arg(long, conflicts_with = "stop")]
+        route: Option<Route>,
+        /// Only disruptions for a stop, by name or identifier (i.e. `Stop:Train/1071`)
+        #[arg(long)]
+        stop: Option<String>,
+        /// Only current or planned disruptions
+        #[arg(long, value_parser = ["current", "planned"])]
+        status: Option<String>,
+    },
+    /// Routes, optionally filtered by type or name
+    Routes {
+        /// Only include these route types (i.e. train,tram)
+        #[arg(long = "route-type", short = 't', value_delimiter = ',')]
+        route_types: Vec<RouteType>,
+        /// Only routes whose name contains this
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Stops
+    Stops {
+        #[command(subcommand)]
+        command: StopsCommand,
+    },
+    /// Details of a run (i.e. `Run:Train/952051`)
+    Run { run: Run },
+    /// Stopping pattern of a run (i.e. `Run:Train/952051`)
+    Pattern { run: Run },
+}
+
+#[derive(Subcommand)]
+enum StopsCommand {
+    /// Stops near a location
+    #[command(allow_negative_numbers = true)]
+    Near {
+        latitude: f64,
+        longitude: f64,
+        /// Maximum distance in metres
+        #[arg(long, short, default_value_t = 300.0)]
+        distance: f64,
+        /// Maximum number of stops
+        #[arg(long, short = 'n')]
+        max_results: Option<i32>,
+        /// Only include these route types (i.e. train,tram)
+        #[arg(long = "route-type", short = 't', value_delimiter = ',')]
+        route_types: Vec<RouteType>,
+    },
+}
+
+/// Contents of the config file
+#[derive(Deserialize)]
+struct Config {
+    devid: String,
+    key: String,
+}
+
+/// Developer id and key, from an explicit config file, the environment, or the default config file
+///
+/// `env` looks up environment variables, so tests don't depend on the real environment.
+fn credentials(
+    config: Option<PathBuf>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<(String, String)> {
+    if config.is_none() {
+        if let (Some(devid), Some(key)) = (env("PTV_DEVID"), env("PTV_KEY")) {
+            return Ok((devid, key));
+        }
+    }
+    let default = env("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("ptv").join("config.toml"));
+    let Some(path) = config.or(default) else {
+        return Err("PTV_DEVID and PTV_KEY not set, and no config file".into());
+    };
+    let text = std::fs::read_to_string(&path).map_err(|e| {
+        format!(
+            "PTV_DEVID and PTV_KEY not set, and failed to read {}: {e}",
+            path.display()
+        )
+    })?;
+    let config: Config =
+        toml::from_str(&text).map_err(|e| format!("Invalid config {}: {e}", path.display()))?;
+    Ok((config.devid, config.key))
+}
+
+/// Output of a command, items paired with their cells in a table
+struct Output<T> {
+    columns: &'static [&'static str],
+    items: Vec<(T, Vec<String>)>,
+}
+
+impl<T: Serialize> Output<T> {
+    /// Write the items as a table, JSON, or NDJSON
+    fn write(&self, format: Format, out: &mut impl Write) -> Result<()> {
+        match format {
+            Format::Table => {
+                let mut widths: Vec<usize> =
+                    self.columns.iter().map(|c| c.chars().count()).collect();
+                for (_, row) in &self.items {
+                    for (width, cell) in widths.iter_mut().zip(row) {
+                        *width = (*width).max(cell.chars().count());
+                    }
+                }
+                let header: Vec<String> = self.columns.iter().map(|c| c.to_string()).collect();
+                let rows = self.items.iter().map(|(_, row)| row);
+                for row in std::iter::once(&header).chain(rows) {
+                    let cells: Vec<String> = row
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, width)| format!("{cell:width$}"))
+                        .collect();
+                    writeln!(out, "{}", cells.join("  ").trim_end())?;
+                }
+            }
+            Format::Json => {
+                let items: Vec<&T> = self.items.iter().map(|(item, _)| item).collect();
+                serde_json::to_writer_pretty(&mut *out, &items)?;
+                writeln!(out)?;
+            }
+            Format::Ndjson => {
+                for (item, _) in &self.items {
+                    serde_json::to_writer(&mut *out, item)?;
+                    writeln!(out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the items to stdout
+    fn print(&self, format: Format) -> Result<()> {
+        self.write(format, &mut std::io::stdout().lock())
+    }
+}
+
+/// Melbourne local time, as `HH:MM`
+fn local_time(datetime: OffsetDateTime) -> String {
+    let local = melbourne_time(datetime);
+    format!("{:02}:{:02}", local.hour(), local.minute())
+}
+
+/// Melbourne local date and time from an API date string, as `YYYY-MM-DD HH:MM`
+fn local_date(date: Option<&str>) -> String {
+    let Some(datetime) = date.and_then(|d| OffsetDateTime::parse(d, &Iso8601::DEFAULT).ok()) else {
+        return String::new();
+    };
+    let local = melbourne_time(datetime);
+    format!("{} {}", local.date(), local_time(datetime))
+}
+
+/// Delay in whole minutes, blank if on time or unknown
+fn delay(delay: Option<time::Duration>) -> String {
+    match delay.map(|d| d.whole_minutes()) {
+        Some(minutes) if minutes != 0 => format!("{minutes:+}"),
+        _ => String::new(),
+    }
+}
+
+/// Find a stop by name or identifier (i.e. `Stop:Train/1071`)
+async fn find_stop(ptv: &PTV, text: &str) -> Result<Stop> {
+    match ptv.resolve_stop(text, ResolveParams::default()).await? {
+        StopResolution::Literal(stop) => Ok(stop),
+        StopResolution::Found(candidate) => Ok(candidate.stop.stop()),
+        StopResolution::Ambiguous(candidates) => {
+            let names: Vec<String> = candidates
+                .iter()
+                .map(|c| format!("\n    {} {}", c.stop.stop(), c.stop.stop_name))
+                .collect();
+            Err(format!("Multiple stops found for '{text}':{}", names.concat()).into())
+        }
+        StopResolution::NotFound => Err(format!("No stop found for '{text}'").into()),
+    }
+}
+
+/// Internal helper to turn an empty list of filters into no filter
+fn filter<T>(values: Vec<T>) -> Option<Vec<T>> {
+    (!values.is_empty()).then_some(values)
+}
+
+/// Item of `ptv search` output
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SearchResult<'a> {
+    Stop(&'a ptv::StopDetails),
+    Route(&'a ptv::RouteDetails),
+}
+
+/// Output of `ptv search`
+fn search_output(response: &SearchResponse) -> Output<SearchResult<'_>> {
+    let stops = response.stops.iter().map(|stop| {
+        let row = vec![
+            stop.stop().to_string(),
+            stop.stop_name.clone(),
+            stop.stop_suburb.clone(),
+        ];
+        (SearchResult::Stop(stop), row)
+    });
+    let routes = response.routes.iter().map(|route| {
+        let row = vec![
+            route.route().to_string(),
+            route.route_name.clone(),
+            route.route_number.clone(),
+        ];
+        (SearchResult::Route(route), row)
+    });
+    Output {
+        columns: &["Id", "Name", "Details"],
+        items: stops.chain(routes).collect(),
+    }
+}
+
+/// Output of `ptv departures`, in order of departure with the time until each from `now`
+fn departures_output<'a>(
+    stop: &Stop,
+    response: &'a DeparturesResponse,
+    now: OffsetDateTime,
+) -> Output<&'a DepartureDetails> {
+    let mut departures: Vec<_> = response.resolved().collect();
+    departures.sort_by_key(|departure| departure.departure_time());
+    let items = departures.into_iter().map(|departure| {
+        let run = Run {
+            route_type: stop.route_type,
+            id: departure.run_ref.clone(),
+        };
+        let row = vec![
+            local_time(departure.departure_time()),
+            format!("{} min", (departure.departure_time() - now).whole_minutes()),
+            departure
+                .route()
+                .map_or_else(String::new, |r| r.route_name.clone()),
+            departure.destination().unwrap_or_default().to_string(),
+            departure.platform().unwrap_or_default().to_string(),
+            delay(departure.delay()),
+            run.to_string(),
+        ];
+        (departure.details(), row)
+    });
+    Output {
+        columns: &[
+            "Time",
+            "In",
+            "Route",
+            "Destination",
+            "Platform",
+            "Delay",
+            "Run",
+        ],
+        items: items.collect(),
+    }
+}
+
+/// Output of `ptv disruptions`
+fn disruptions_output(response: &DisruptionsResponse) -> Output<&DisruptionDetails> {
+    let items = response.disruptions.iter().map(|disruption| {
+        let row = vec![
+            disruption.disruption_id.to_string(),
+            disruption.disruption_status.clone(),
+            local_date(disruption.from_date.as_deref()),
+            local_date(disruption.to_date.as_deref()),
+            disruption.title.clone(),
+        ];
+        (disruption, row)
+    });
+    Output {
+        columns: &["Id", "Status", "From", "To", "Title"],
+        items: items.collect(),
+    }
+}
+
+/// Output of `ptv routes`
+fn routes_output(response: &RoutesResponse) -> Output<&RouteDetails> {
+    let items = response.routes.iter().map(|route| {
+        let row = vec![
+            route.route().to_string(),
+            route.route_number.clone(),
+            route.route_name.clone(),
+        ];
+        (route, row)
+    });
+    Output {
+        columns: &["Route", "Number", "Name"],
+        items: items.collect(),
+    }
+}
+
+/// Output of `ptv stops near`
+fn stops_output(response: &StopsByDistanceResponse) -> Output<&StopDetails> {
+    let items = response.stops.iter().map(|stop| {
+        let row = vec![
+            stop.stop().to_string(),
+            stop.stop_name.clone(),
+            stop.stop_suburb.clone(),
+            format!("{:.0} m", stop.stop_distance),
+        ];
+        (stop, row)
+    });
+    Output {
+        columns: &["Stop", "Name", "Suburb", "Distance"],
+        items: items.collect(),
+    }
+}
+
+/// Output of `ptv run`
+fn run_output<'a>(run: &Run, response: &'a RunResponse) -> Output<&'a RunDetails> {
+    let details = &response.run;
+    let route = Route {
+        route_type: details.route_type,
+        id: details.route_id.clone(),
+    };
+    let vehicle = details
+        .vehicle_descriptor
+        .as_ref()
+        .and_then(|v| v.description.clone())
+        .unwrap_or_default();
+    let row = vec![
+        run.to_string(),
+        route.to_string(),
+        details.destination_name.clone(),
+        details.status.clone(),
+        vehicle,
+    ];
+    Output {
+        columns: &["Run", "Route", "Destination", "Status", "Vehicle"],
+        items: vec![(details, row)],
+    }
+}
+
+/// Output of `ptv pattern`
+fn pattern_output(response: &PatternResponse) -> Output<&DepartureDetails> {
+    let items = response.departures.iter().map(|departure| {
+        let stop = response.stops.get(&departure.stop_id);
+        let time = departure
+            .estimated_departure_utc
+            .unwrap_or(departure.scheduled_departure_utc);
+        let late = departure
+            .estimated_departure_utc
+            .map(|estimated| estimated - departure.scheduled_departure_utc);
+        let row = vec![
+            local_time(time),
+            stop.map_or_else(|| departure.stop_id.to_string(), |s| s.stop_name.clone()),
+            departure.platform_number.clone().unwrap_or_default(),
+            delay(late),
+        ];
+        (departure, row)
+    });
+    Output {
+        columns: &["Time", "Stop", "Platform", "Delay"],
+        items: items.collect(),
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let (devid, key) = credentials(cli.config, |name| std::env::var(name).ok())?;
+    let ptv = PTV::new(devid, key, concat!("ptv-cli/", env!("CARGO_PKG_VERSION")));
+    let format = cli.format;
+
+    match cli.command {
+        Command::Search { text, route_types } => {
+            let params = SearchParams {
+                route_types: filter(route_types),
+                include_outlets: Some(false),
+                ..Default::default()
+            };
+            let response = ptv.search(&text, params).await?;
+            search_output(&response).print(format)
+        }
+        Command::Departures {
+            stop,
+            max_results,
+            platforms,
+        } => {
+            let stop = find_stop(&ptv, &stop).await?;
+            let params = DeparturesParams {
+                platform_numbers: filter(platforms),
+                max_results: Some(max_results),
+                expand: Some(vec![
+                    ResponseExpand::Route,
+                    ResponseExpand::Run,
+                    ResponseExpand::Stop,
+                ]),
+                ..Default::default()
+            };
+            let response = ptv.departures(&stop, params).await?;
+            departures_output(&stop, &response, OffsetDateTime::now_utc()).print(format)
+        }
+        Command::Disruptions {
+            route_types,
+            route,
+            stop,
+            status,
+        } => {
+            let params = DisruptionsParams {
+                route_types: filter(route_types),
+                disruption_status: status,
+                ..Default::default()
+            };
+            let response = match (route, stop) {
+                (Some(route), _) => ptv.disruptions_for_route(&route.id, params).await?,
+                (None, Some(stop)) => {
+                    let stop = find_stop(&ptv, &stop).await?;
+                    ptv.disruptions_for_stop(&stop.id, params).await?
+                }
+                (None, None) => ptv.disruptions(params).await?,
+            };
+            disruptions_output(&response).print(format)
+        }
+        Command::Routes { route_types, name } => {
+            let params = RoutesParams {
+                route_types: filter(route_types),
+                route_name: name,
+            };
+            let response = ptv.routes(params).await?;
+            routes_output(&response).print(format)
+        }
+        Command::Stops {
+            command:
+                StopsCommand::Near {
+                    latitude,
+                    longitude,
+                    distance,
+                    max_results,
+                    route_types,
+                },
+        } => {
+            let params = StopsByDistanceParams {
+                route_types: filter(route_types),
+                max_results,
+                max_distance: Some(distance),
+                ..Default::default()
+            };
+            let location = ptv::LatLon::new(latitude, longitude);
+            let response = ptv.stops_near(location, params).await?;
+            stops_output(&response).print(format)
+        }
+        Command::Run { run } => {
+            let params = RunsParams {
+                expand: Some(vec![ResponseExpand::VehicleDescriptor]),
+                ..Default::default()
+            };
+            let response = ptv.run(&run, params).await?;
+            run_output(&run, &response).print(format)
+        }
+        Command::Pattern { run } => {
+            let params = PatternParams {
+                expand: Some(vec![ResponseExpand::Stop]),
+                ..Default::default()
+            };
+            let response = ptv.pattern(&run, params).await?;
+            pattern_output(&response).print(format)
+        }
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("ptv: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use super::*;
+use clap::error::ErrorKind;
+use clap::CommandFactory;
+use std::collections::HashMap;
+use time::macros::datetime;
+
+/// Internal helper to parse a fixture in `testdata`
+fn fixture<T: serde::de::DeserializeOwned>(name: &str) -> T {
+    let data = std::fs::read_to_string(format!("testdata/{name}.json")).unwrap();
+    serde_json::from_str(&data).unwrap()
+}
+
+/// Internal helper to write output in a format
+fn written<T: Serialize>(output: &Output<T>, format: Format) -> String {
+    let mut out = Vec::new();
+    output.write(format, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_arguments() {
+    Cli::command().debug_assert();
+
+    let cli = Cli::try_parse_from([
+        "ptv",
+        "departures",
+        "Flinders Street",
+        "-n",
+        "3",
+        "--platform",
+        "1,2",
+        "--format",
+        "ndjson",
+    ])
+    .unwrap();
+    assert!(matches!(cli.format, Format::Ndjson));
+    let Command::Departures {
+        stop,
+        max_results,
+        platforms,
+    } = cli.command
+    else {
+        panic!("Expected departures");
+    };
+    assert_eq!(stop, "Flinders Street");
+    assert_eq!(max_results, 3);
+    assert_eq!(platforms, [1, 2]);
+
+    let cli = Cli::try_parse_from(["ptv", "search", "Flinders", "-t", "train,tram"]).unwrap();
+    assert!(matches!(cli.format, Format::Table));
+    let Command::Search { text, route_types } = cli.command else {
+        panic!("Expected search");
+    };
+    assert_eq!(text, "Flinders");
+    assert_eq!(route_types, [RouteType::TRAIN, RouteType::TRAM]);
+
+    // Negative coordinates aren't options
+    let cli =
+        Cli::try_parse_from(["ptv", "stops", "near", "-37.8183", "144.9671", "-d", "500"]).unwrap();
+    let Command::Stops {
+        command:
+            StopsCommand::Near {
+                latitude,
+                longitude,
+                distance,
+                ..
+            },
+    } = cli.command
+    else {
+        panic!("Expected stops near");
+    };
+    assert_eq!((latitude, longitude, distance), (-37.8183, 144.9671, 500.0));
+
+    let cli = Cli::try_parse_from(["ptv", "run", "Run:Train/952051"]).unwrap();
+    let Command::Run { run } = cli.command else {
+        panic!("Expected run");
+    };
+    assert_eq!(run.to_string(), "Run:Train/952051");
+
+    let error = |args: &[&str]| Cli::try_parse_from(args).err().unwrap().kind();
+    assert_eq!(
+        error(&[
+            "ptv",
+            "disruptions",
+            "--route",
+            "Route:Train/14",
+            "--stop",
+            "1071"
+        ]),
+        ErrorKind::ArgumentConflict
+    );
+    assert_eq!(
+        error(&["ptv", "disruptions", "--status", "closed"]),
+        ErrorKind::InvalidValue
+    );
+    assert_eq!(error(&["ptv", "run", "952051"]), ErrorKind::ValueValidation);
+    assert_eq!(
+        error(&["ptv", "routes", "--format", "xml"]),
+        ErrorKind::InvalidValue
+    );
+}
+
+#[test]
+fn test_credentials() {
+    let dir = std::env::temp_dir().join(format!("ptv-cli-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("ptv")).unwrap();
+    let default = dir.join("ptv").join("config.toml");
+    std::fs::write(&default, "devid = \"1\"\nkey = \"default\"\n").unwrap();
+    let explicit = dir.join("explicit.toml");
+    std::fs::write(&explicit, "devid = \"2\"\nkey = \"explicit\"\n").unwrap();
+    let invalid = dir.join("invalid.toml");
+    std::fs::write(&invalid, "devid = 3\n").unwrap();
+
+    let env = |vars: &[(&str, &str)]| {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name: &str| vars.get(name).cloned()
+    };
+    let config_home = dir.to_str().unwrap();
+    let all = env(&[
+        ("PTV_DEVID", "0"),
+        ("PTV_KEY", "environment"),
+        ("XDG_CONFIG_HOME", config_home),
+    ]);
+
+    // The environment is used before the default config file, but not an explicit one
+    assert_eq!(
+        credentials(None, &all).unwrap(),
+        ("0".to_string(), "environment".to_string())
+    );
+    assert_eq!(
+        credentials(Some(explicit.clone()), &all).unwrap(),
+        ("2".to_string(), "explicit".to_string())
+    );
+
+    // Both variables are needed, otherwise the default config file is used
+    let partial = env(&[("PTV_DEVID", "0"), ("XDG_CONFIG_HOME", config_home)]);
+    assert_eq!(
+        credentials(None, &partial).unwrap(),
+        ("1".to_string(), "default".to_string())
+    );
+    let home = env(&[("HOME", dir.parent().unwrap().to_str().unwrap())]);
+    let error = credentials(None, &home).unwrap_err().to_string();
+    assert!(error.contains(".config"), "{error}");
+
+    let error = credentials(None, env(&[])).unwrap_err().to_string();
+    assert_eq!(error, "PTV_DEVID and PTV_KEY not set, and no config file");
+    let error = credentials(Some(invalid.clone()), &all)
+        .unwrap_err()
+        .to_string();
+    assert!(error.starts_with("Invalid config"), "{error}");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_table() {
+    let response: DeparturesResponse = fixture("departures_0_1181");
+    let stop = Stop {
+        route_type: RouteType::TRAIN,
+        id: ptv::StopId::new(1181),
+    };
+    let now = datetime!(2023-07-20 09:30 UTC);
+    let table = written(&departures_output(&stop, &response, now), Format::Table);
+    let lines: Vec<_> = table.lines().collect();
+    assert_eq!(lines.len(), 7);
+    assert_eq!(
+        lines[0],
+        "Time   In      Route        Destination  Platform  Delay  Run"
+    );
+    assert_eq!(
+        lines[1],
+        "19:36  6 min   Sunbury      Sunshine     14        +1     Run:Train/952051"
+    );
+    // Trailing blank cells are trimmed
+    assert_eq!(
+        lines[2],
+        "19:38  8 min   Craigieburn  Craigieburn  13               Run:Train/951843"
+    );
+
+    let response: DisruptionsResponse = fixture("disruptions");
+    let table = written(&disruptions_output(&response), Format::Table);
+    let lines: Vec<_> = table.lines().collect();
+    assert!(lines[1].starts_with("287344  Current  2023-07-17 20:30  2023-07-21 03:00  Buses"));
+    assert_eq!(
+        lines[2],
+        "287901  Planned  2023-07-22 06:00                    Geelong line: Reservations required on selected services"
+    );
+
+    let response: StopsByDistanceResponse = fixture("stops_location");
+    let table = written(&stops_output(&response), Format::Table);
+    assert_eq!(
+        table.lines().nth(1),
+        Some("Stop:Tram/2479   Bourke St/Swanston St #5        Melbourne City  230 m")
+    );
+
+    let response: RunResponse = fixture("run_0_952051");
+    let run: Run = "Run:Train/952051".parse().unwrap();
+    let table = written(&run_output(&run, &response), Format::Table);
+    assert_eq!(
+        table,
+        "Run               Route           Destination  Status     Vehicle\n\
+         Run:Train/952051  Route:Train/14  Sunshine     scheduled  6 Car Comeng\n"
+    );
+
+    // Only the header when there's nothing to show
+    let output: Output<()> = Output {
+        columns: &["Route", "Number", "Name"],
+        items: Vec::new(),
+    };
+    assert_eq!(written(&output, Format::Table), "Route  Number  Name\n");
+}
+
+#[test]
+fn test_json() {
+    let response: SearchResponse = fixture("search_stops");
+    let json = written(&search_output(&response), Format::Json);
+    let items: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+    assert_eq!(items.len(), response.stops.len() + response.routes.len());
+    assert_eq!(items[0]["type"], "stop");
+    assert_eq!(items[0]["stop_name"], "Southern Cross Station");
+    assert_eq!(items[0]["route_type"], 0);
+
+    let response: RoutesResponse = fixture("gtfs/routes");
+    let json = written(&routes_output(&response), Format::Json);
+    let items: Vec<RouteDetails> = serde_json::from_str(&json).unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].route_name, "Lilydale");
+
+    let output: Output<()> = Output {
+        columns: &["Route"],
+        items: Vec::new(),
+    };
+    assert_eq!(written(&output, Format::Json), "[]\n");
+}
+
+#[test]
+fn test_ndjson() {
+    let response: PatternResponse = fixture("planner/pattern_0_950001");
+    let ndjson = written(&pattern_output(&response), Format::Ndjson);
+    let departures: Vec<DepartureDetails> = ndjson
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(departures.len(), response.departures.len());
+    assert_eq!(departures[1].stop_id, response.departures[1].stop_id);
+    assert_eq!(departures[1].departure_sequence, 2);
+
+    let output: Output<()> = Output {
+        columns: &["Route"],
+        items: Vec::new(),
+    };
+    assert_eq!(written(&output, Format::Ndjson), "");
+}
@@ -0,0 +1,206 @@
+use crate::*;
+use std::collections::HashMap;
+use std::future::ready;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A recorded request and its response, see [Cassette]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Interaction {
+    /// Unsigned path and query of the request (i.e. `/v3/route_types`), see [cassette_key]
+    pub request: String,
+    /// HTTP status of the response
+    pub status: u16,
+    /// JSON body of the response (or a string, if the body wasn't JSON)
+    pub response: serde_json::Value,
+}
+
+/// Requests and responses recorded by a [RecordingClient], to be served by a [ReplayClient]
+///
+/// Cassettes are stored as JSON files. They never contain the `devid` or `signature` of a
+/// request, so they're safe to commit alongside tests.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Cassette {
+    /// Interactions, in the order they were recorded
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// An empty cassette
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a cassette from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|error| Error::Other(format!("Failed to read {}: {error}", path.display())))?;
+        parse_json(&json)
+    }
+
+    /// Write the cassette to a JSON file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json + "\n")
+            .map_err(|error| Error::Other(format!("Failed to write {}: {error}", path.display())))
+    }
+}
+
+/// Key used to match requests in a [Cassette], the path and query without `devid` or `signature`
+pub fn cassette_key(url: &Url) -> String {
+    let query = strip_credentials(url.query().unwrap_or_default());
+    if query.is_empty() {
+        url.path().to_string()
+    } else {
+        format!("{}?{query}", url.path())
+    }
+}
+
+/// HTTP client which records the responses of another client into a [Cassette]
+///
+/// Successful responses and errors returned by the API or HTTP server are recorded. Other
+/// errors (i.e. connection failures) aren't, as there's no response to replay. Clones share
+/// the same recording, so one can be kept to save it after the other is given to [PTV].
+#[derive(Debug)]
+pub struct RecordingClient<C> {
+    inner: Arc<C>,
+    cassette: Arc<Mutex<Cassette>>,
+}
+impl<C> Clone for RecordingClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            cassette: self.cassette.clone(),
+        }
+    }
+}
+
+impl<C: PTVHttpClient> RecordingClient<C> {
+    /// Record the responses of `inner`
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            cassette: Default::default(),
+        }
+    }
+
+    /// Everything recorded so far
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().expect("Cassette poisoned").clone()
+    }
+
+    /// Write everything recorded so far to a JSON file, see [Cassette::save]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.cassette().save(path)
+    }
+}
+
+impl<C: PTVHttpClient> PTVHttpClient for RecordingClient<C> {
+    fn api_get<T: DeserializeOwned + Send + 'static>(&self, url: Url) -> FutureResult<T> {
+        let request = cassette_key(&url);
+        let response = self.inner.api_get::<serde_json::Value>(url);
+        let cassette = self.cassette.clone();
+        Box::pin(async move {
+            let (status, response) = match response.await {
+                Ok(json) => (StatusCode::OK, json),
+                Err(Error::API(status, error)) => (status, serde_json::to_value(error)?),
+                Err(Error::HTTP(status, body)) => (status, serde_json::Value::String(body)),
+                Err(error) => return Err(error),
+            };
+            cassette
+                .lock()
+                .expect("Cassette poisoned")
+                .interactions
+                .push(Interaction {
+                    request,
+                    status: status.as_u16(),
+                    response: response.clone(),
+                });
+            replay(status, &response)
+        })
+    }
+}
+
+/// HTTP client which serves responses from a [Cassette], without a network
+///
+/// Requests are matched by [cassette_key], so the credentials used don't matter. If the same
+/// request was recorded more than once, the responses are served in order and the last one is
+/// repeated. Requests which weren't recorded fail with [Error::Other], naming the request.
+#[derive(Debug)]
+pub struct ReplayClient {
+    interactions: HashMap<String, Vec<Interaction>>,
+    served: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplayClient {
+    /// Serve the responses in a cassette
+    pub fn new(cassette: Cassette) -> Self {
+        let mut interactions: HashMap<String, Vec<Interaction>> = HashMap::new();
+        for interaction in cassette.interactions {
+            interactions
+                .entry(interaction.request.clone())
+                .or_default()
+                .push(interaction);
+        }
+        Self {
+            interactions,
+            served: Default::default(),
+        }
+    }
+
+    /// Serve the responses in a cassette file, see [Cassette::load]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Cassette::load(path).map(Self::new)
+    }
+
+    /// Internal helper to find the next response for a request
+    fn next(&self, request: &str) -> Option<&Interaction> {
+        let interactions = self.interactions.get(request)?;
+        let mut served = self.served.lock().expect("Replay poisoned");
+        let count = served.entry(request.to_string()).or_default();
+        let interaction = interactions.get(*count).or(interactions.last());
+        *count += 1;
+        interaction
+    }
+}
+
+impl PTVHttpClient for ReplayClient {
+    fn api_get<T: DeserializeOwned + Send + 'static>(&self, url: Url) -> FutureResult<T> {
+        let request = cassette_key(&url);
+        let result = match self.next(&request) {
+            Some(interaction) => match StatusCode::from_u16(interaction.status) {
+                Ok(status) => replay(status, &interaction.response),
+                Err(_) => Err(Error::Other(format!(
+                    "Invalid status {} recorded for {request}",
+                    interaction.status
+                ))),
+            },
+            None => {
+                let mut recorded: Vec<&str> =
+                    self.interactions.keys().map(String::as_str).collect();
+                recorded.sort_unstable();
+                Err(Error::Other(format!(
+                    "No recorded response for {request} (recorded: {})",
+                    recorded.join(", ")
+                )))
+            }
+        };
+        Box::pin(ready(result))
+    }
+}
+
+/// Internal helper to turn a recorded response into a result, as the `reqwest` client would
+fn replay<T: DeserializeOwned>(status: StatusCode, response: &serde_json::Value) -> Result<T> {
+    if status.is_success() {
+        return parse_json(&response.to_string());
+    }
+    match response {
+        serde_json::Value::String(body) => Err(Error::HTTP(status, body.clone())),
+        _ => match serde_json::from_value(response.clone()) {
+            Ok(error) => Err(Error::API(status, error)),
+            Err(_) => Err(Error::HTTP(status, response.to_string())),
+        },
+    }
+}
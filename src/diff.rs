@@ -0,0 +1,164 @@
+use crate::*;
+use std::collections::BTreeSet;
+
+/// A change to a departure between two snapshots, see [diff]
+#[derive(Clone, Debug)]
+pub enum DepartureChange {
+    /// A departure which wasn't in the previous snapshot
+    Added(DepartureDetails),
+    /// A departure which is no longer in the current snapshot (i.e. it departed, or the run was removed)
+    Vanished(DepartureDetails),
+    /// The real-time estimate of the departure time changed, by any amount
+    EstimateChanged {
+        departure: DepartureDetails,
+        previous: Option<OffsetDateTime>,
+        current: Option<OffsetDateTime>,
+    },
+    /// The real-time delay changed (in whole minutes, `None` when there's no real-time estimate)
+    ///
+    /// This is coarser than [DepartureChange::EstimateChanged], which is also reported.
+    DelayChanged {
+        departure: DepartureDetails,
+        previous: Option<i64>,
+        current: Option<i64>,
+    },
+    /// The platform changed
+    PlatformChanged {
+        departure: DepartureDetails,
+        previous: Option<String>,
+        current: Option<String>,
+    },
+    /// The service arrived at, or left, the platform
+    AtPlatformChanged {
+        departure: DepartureDetails,
+        at_platform: bool,
+    },
+    /// The run was cancelled (requires `include_cancelled` and `Run` expansion)
+    Cancelled(DepartureDetails),
+    /// A disruption now affects the departure
+    DisruptionAdded {
+        departure: DepartureDetails,
+        disruption_id: DisruptionId,
+    },
+    /// A disruption no longer affects the departure
+    DisruptionRemoved {
+        departure: DepartureDetails,
+        disruption_id: DisruptionId,
+    },
+}
+impl DepartureChange {
+    /// The departure this change applies to (from the current snapshot, unless it vanished)
+    pub fn departure(&self) -> &DepartureDetails {
+        match self {
+            Self::Added(departure)
+            | Self::Vanished(departure)
+            | Self::Cancelled(departure)
+            | Self::EstimateChanged { departure, .. }
+            | Self::DelayChanged { departure, .. }
+            | Self::PlatformChanged { departure, .. }
+            | Self::AtPlatformChanged { departure, .. }
+            | Self::DisruptionAdded { departure, .. }
+            | Self::DisruptionRemoved { departure, .. } => departure,
+        }
+    }
+}
+
+/// Key used to match departures across snapshots
+type DepartureKey<'a> = (&'a RunRef, &'a StopId, i32);
+
+fn departure_key(departure: &DepartureDetails) -> DepartureKey<'_> {
+    (
+        &departure.run_ref,
+        &departure.stop_id,
+        departure.departure_sequence,
+    )
+}
+
+/// Real-time delay in whole minutes
+fn delay_minutes(departure: &DepartureDetails) -> Option<i64> {
+    let estimated = departure.estimated_departure_utc?;
+    Some((estimated - departure.scheduled_departure_utc).whole_minutes())
+}
+
+fn is_cancelled(response: &DeparturesResponse, departure: &DepartureDetails) -> bool {
+    response
+        .runs
+        .get(&departure.run_ref)
+        .is_some_and(|run| run.status.eq_ignore_ascii_case("cancelled"))
+}
+
+/// Find the changes between two snapshots of departures
+///
+/// Departures are matched by `(run_ref, stop_id, departure_sequence)`. The output only depends
+/// on the two snapshots; it's ordered by that key, with vanished departures last.
+pub fn diff(previous: &DeparturesResponse, current: &DeparturesResponse) -> Vec<DepartureChange> {
+    let before: BTreeMap<_, _> = previous
+        .departures
+        .iter()
+        .map(|departure| (departure_key(departure), departure))
+        .collect();
+    let after: BTreeMap<_, _> = current
+        .departures
+        .iter()
+        .map(|departure| (departure_key(departure), departure))
+        .collect();
+
+    let mut changes = Vec::new();
+    for (key, &new) in &after {
+        let Some(&old) = before.get(key) else {
+            changes.push(DepartureChange::Added(new.clone()));
+            continue;
+        };
+        if old.estimated_departure_utc != new.estimated_departure_utc {
+            changes.push(DepartureChange::EstimateChanged {
+                departure: new.clone(),
+                previous: old.estimated_departure_utc,
+                current: new.estimated_departure_utc,
+            });
+        }
+        let (previous_delay, current_delay) = (delay_minutes(old), delay_minutes(new));
+        if previous_delay != current_delay {
+            changes.push(DepartureChange::DelayChanged {
+                departure: new.clone(),
+                previous: previous_delay,
+                current: current_delay,
+            });
+        }
+        if old.platform_number != new.platform_number {
+            changes.push(DepartureChange::PlatformChanged {
+                departure: new.clone(),
+                previous: old.platform_number.clone(),
+                current: new.platform_number.clone(),
+            });
+        }
+        if old.at_platform != new.at_platform {
+            changes.push(DepartureChange::AtPlatformChanged {
+                departure: new.clone(),
+                at_platform: new.at_platform,
+            });
+        }
+        if is_cancelled(current, new) && !is_cancelled(previous, old) {
+            changes.push(DepartureChange::Cancelled(new.clone()));
+        }
+        let old_disruptions: BTreeSet<_> = old.disruption_ids.iter().collect();
+        let new_disruptions: BTreeSet<_> = new.disruption_ids.iter().collect();
+        for &disruption_id in new_disruptions.difference(&old_disruptions) {
+            changes.push(DepartureChange::DisruptionAdded {
+                departure: new.clone(),
+                disruption_id: disruption_id.clone(),
+            });
+        }
+        for &disruption_id in old_disruptions.difference(&new_disruptions) {
+            changes.push(DepartureChange::DisruptionRemoved {
+                departure: new.clone(),
+                disruption_id: disruption_id.clone(),
+            });
+        }
+    }
+    for (key, &old) in &before {
+        if !after.contains_key(key) {
+            changes.push(DepartureChange::Vanished(old.clone()));
+        }
+    }
+    changes
+}
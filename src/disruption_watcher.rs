@@ -0,0 +1,179 @@
+use crate::*;
+
+/// What a [DisruptionWatcher] polls disruptions for
+#[derive(Clone, Debug)]
+pub enum DisruptionTarget {
+    /// All disruptions for some route types
+    RouteTypes(Vec<RouteType>),
+    /// Disruptions for a route
+    Route(RouteId),
+    /// Disruptions for a stop
+    Stop(StopId),
+}
+
+/// A field of a disruption which changed, see [DisruptionEvent::Updated]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldChange {
+    /// Name of the field, as returned by the API
+    pub field: String,
+    /// Previous value (`null` if it wasn't present)
+    pub previous: serde_json::Value,
+    /// Current value (`null` if it's no longer present)
+    pub current: serde_json::Value,
+}
+
+/// A change to disruptions, returned by [DisruptionWatcher::poll]
+#[derive(Clone, Debug)]
+pub enum DisruptionEvent {
+    /// A disruption which hasn't been seen before
+    New(DisruptionDetails),
+    /// A disruption which was updated since it was last seen
+    Updated {
+        disruption: DisruptionDetails,
+        changes: Vec<FieldChange>,
+    },
+    /// A disruption which is no longer returned
+    Resolved(DisruptionDetails),
+}
+impl DisruptionEvent {
+    /// The disruption this event applies to (as last seen, if resolved)
+    pub fn disruption(&self) -> &DisruptionDetails {
+        match self {
+            Self::New(disruption)
+            | Self::Resolved(disruption)
+            | Self::Updated { disruption, .. } => disruption,
+        }
+    }
+}
+
+/// Internal helper to find the fields which differ between two versions of a disruption
+fn field_changes(previous: &DisruptionDetails, current: &DisruptionDetails) -> Vec<FieldChange> {
+    let to_map = |disruption: &DisruptionDetails| match serde_json::to_value(disruption) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => Default::default(),
+    };
+    let (previous, current) = (to_map(previous), to_map(current));
+    let mut fields: Vec<&String> = previous.keys().chain(current.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter_map(|field| {
+            let before = previous.get(field).cloned().unwrap_or_default();
+            let after = current.get(field).cloned().unwrap_or_default();
+            (before != after).then(|| FieldChange {
+                field: field.clone(),
+                previous: before,
+                current: after,
+            })
+        })
+        .collect()
+}
+
+/// Polls disruptions for some route types, routes or stops, and reports what changed
+///
+/// Disruptions are tracked by identifier. A disruption is updated when its `last_updated` or any
+/// other field changes. The seen disruptions can be saved with [DisruptionWatcher::write_state]
+/// and restored with [DisruptionWatcher::read_state], so a restart doesn't report them again.
+#[derive(Clone, Debug)]
+pub struct DisruptionWatcher {
+    targets: Vec<DisruptionTarget>,
+    params: DisruptionsParams,
+    seen: BTreeMap<DisruptionId, DisruptionDetails>,
+}
+
+impl DisruptionWatcher {
+    /// Watch disruptions for some targets, filtered by `params` (e.g. only "current" disruptions)
+    pub fn new(targets: Vec<DisruptionTarget>, params: DisruptionsParams) -> Self {
+        Self {
+            targets,
+            params,
+            seen: BTreeMap::new(),
+        }
+    }
+
+    /// Disruptions seen in the last poll
+    pub fn seen(&self) -> &BTreeMap<DisruptionId, DisruptionDetails> {
+        &self.seen
+    }
+
+    /// Fetch disruptions for every target, and report the changes since the last poll
+    ///
+    /// If any request fails nothing is changed, so an outage isn't reported as everything resolving.
+    pub async fn poll<HTTPClient: PTVHttpClient>(
+        &mut self,
+        ptv: &PTV<HTTPClient>,
+    ) -> Result<Vec<DisruptionEvent>> {
+        let mut current = Vec::new();
+        for target in &self.targets {
+            let params = self.params.clone();
+            let response = match target {
+                DisruptionTarget::RouteTypes(route_types) => {
+                    let params = DisruptionsParams {
+                        route_types: Some(route_types.clone()),
+                        ..params
+                    };
+                    ptv.disruptions(params).await?
+                }
+                DisruptionTarget::Route(route_id) => {
+                    ptv.disruptions_for_route(route_id, params).await?
+                }
+                DisruptionTarget::Stop(stop_id) => {
+                    ptv.disruptions_for_stop(stop_id, params).await?
+                }
+            };
+            current.extend(response.disruptions.iter().cloned());
+        }
+        Ok(self.update(current))
+    }
+
+    /// Report the changes from the disruptions seen last, to `current`
+    pub fn update(
+        &mut self,
+        current: impl IntoIterator<Item = DisruptionDetails>,
+    ) -> Vec<DisruptionEvent> {
+        let current: BTreeMap<DisruptionId, DisruptionDetails> = current
+            .into_iter()
+            .map(|disruption| (disruption.disruption_id.clone(), disruption))
+            .collect();
+
+        let mut events = Vec::new();
+        for (id, disruption) in &current {
+            match self.seen.get(id) {
+                None => events.push(DisruptionEvent::New(disruption.clone())),
+                Some(previous) => {
+                    let changes = field_changes(previous, disruption);
+                    if !changes.is_empty() {
+                        events.push(DisruptionEvent::Updated {
+                            disruption: disruption.clone(),
+                            changes,
+                        });
+                    }
+                }
+            }
+        }
+        let previous = std::mem::replace(&mut self.seen, current);
+        for (id, disruption) in previous {
+            if !self.seen.contains_key(&id) {
+                events.push(DisruptionEvent::Resolved(disruption));
+            }
+        }
+        events
+    }
+
+    /// Save the disruptions seen in the last poll, as JSON
+    pub fn write_state(&self, writer: impl std::io::Write) -> Result<()> {
+        serde_json::to_writer(writer, &self.seen)
+            .map_err(|e| Error::Other(format!("Failed to write disruption state: {e}")))
+    }
+
+    /// Restore the disruptions seen, as saved by [DisruptionWatcher::write_state]
+    pub fn read_state(&mut self, mut reader: impl std::io::Read) -> Result<()> {
+        let mut data = String::new();
+        reader
+            .read_to_string(&mut data)
+            .map_err(|e| Error::Other(format!("Failed to read disruption state: {e}")))?;
+        self.seen = parse_json(&data)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use crate::*;
+use std::str::FromStr;
+
+/// Mean radius of the Earth (in metres)
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A geographic coordinate, latitude and longitude in degrees (WGS 84)
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LatLon {
+    /// Geographic coordinate of latitude
+    pub latitude: f64,
+    /// Geographic coordinate of longitude
+    pub longitude: f64,
+}
+impl LatLon {
+    /// Create from latitude and longitude in degrees
+    pub const fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+    /// Great-circle distance to another coordinate (in metres), using the haversine formula
+    pub fn distance(&self, other: &LatLon) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().asin()
+    }
+    /// Initial compass bearing to another coordinate, clockwise from True North (in degrees `0..360`)
+    pub fn bearing(&self, other: &LatLon) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let y = dlon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+    /// Whether another coordinate is within `radius` metres of this one
+    pub fn within_radius(&self, other: &LatLon, radius: f64) -> bool {
+        self.distance(other) <= radius
+    }
+    /// The smallest bounding box which contains every point within `radius` metres
+    pub fn bounding_box(&self, radius: f64) -> BoundingBox {
+        let dlat = (radius / EARTH_RADIUS).to_degrees();
+        let dlon = dlat / self.latitude.to_radians().cos().max(f64::EPSILON);
+        BoundingBox {
+            south_west: LatLon::new(self.latitude - dlat, self.longitude - dlon),
+            north_east: LatLon::new(self.latitude + dlat, self.longitude + dlon),
+        }
+    }
+}
+impl std::fmt::Display for LatLon {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{},{}", self.latitude, self.longitude)
+    }
+}
+
+/// A rectangular area aligned to latitude and longitude
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct BoundingBox {
+    /// Corner with the minimum latitude and longitude
+    pub south_west: LatLon,
+    /// Corner with the maximum latitude and longitude
+    pub north_east: LatLon,
+}
+impl BoundingBox {
+    /// The smallest bounding box containing all the coordinates, `None` if there are none
+    pub fn from_points(points: impl IntoIterator<Item = LatLon>) -> Option<Self> {
+        points.into_iter().fold(None, |bounds, point| {
+            let Some(BoundingBox {
+                south_west,
+                north_east,
+            }) = bounds
+            else {
+                return Some(BoundingBox {
+                    south_west: point,
+                    north_east: point,
+                });
+            };
+            Some(BoundingBox {
+                south_west: LatLon::new(
+                    south_west.latitude.min(point.latitude),
+                    south_west.longitude.min(point.longitude),
+                ),
+                north_east: LatLon::new(
+                    north_east.latitude.max(point.latitude),
+                    north_east.longitude.max(point.longitude),
+                ),
+            })
+        })
+    }
+    /// Whether the coordinate is inside (or on the edge of) this bounding box
+    pub fn contains(&self, point: &LatLon) -> bool {
+        (self.south_west.latitude..=self.north_east.latitude).contains(&point.latitude)
+            && (self.south_west.longitude..=self.north_east.longitude).contains(&point.longitude)
+    }
+    /// The centre point of this bounding box
+    pub fn center(&self) -> LatLon {
+        LatLon::new(
+            (self.south_west.latitude + self.north_east.latitude) / 2.0,
+            (self.south_west.longitude + self.north_east.longitude) / 2.0,
+        )
+    }
+}
+
+/// A line made of a sequence of coordinates
+///
+/// The API sends these as a string of coordinate pairs, i.e. `"-37.8183, 144.9671 -37.8181, 144.9669"`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polyline(pub Vec<LatLon>);
+impl std::fmt::Display for Polyline {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (index, point) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}, {}", point.latitude, point.longitude)?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for Polyline {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| Error::Other(format!("'{value}' is not a coordinate")))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if values.len() % 2 != 0 {
+            return Err(Error::Other(format!("'{s}' has an odd number of values")));
+        }
+        Ok(Self(
+            values
+                .chunks_exact(2)
+                .map(|pair| LatLon::new(pair[0], pair[1]))
+                .collect(),
+        ))
+    }
+}
+impl Serialize for Polyline {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+impl<'de> Deserialize<'de> for Polyline {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Combine GeoJSON `Feature`s and `FeatureCollection`s into a single `FeatureCollection`
+pub fn geojson_collection(items: impl IntoIterator<Item = serde_json::Value>) -> serde_json::Value {
+    let mut features = Vec::new();
+    for mut item in items {
+        if item["type"] == "FeatureCollection" {
+            if let Some(serde_json::Value::Array(children)) =
+                item.get_mut("features").map(serde_json::Value::take)
+            {
+                features.extend(children);
+            }
+        } else {
+            features.push(item);
+        }
+    }
+    serde_json::json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+/// GeoJSON positions are longitude first
+fn geojson_position(point: &LatLon) -> serde_json::Value {
+    serde_json::json!([point.longitude, point.latitude])
+}
+
+impl StopDetails {
+    /// Export the stop as a GeoJSON `Feature` with a `Point` geometry
+    pub fn to_geojson(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "Feature",
+            "id": self.stop().to_string(),
+            "geometry": {
+                "type": "Point",
+                "coordinates": geojson_position(&self.location()),
+            },
+            "properties": {
+                "stop_id": self.stop_id,
+                "stop_name": self.stop_name,
+                "stop_suburb": self.stop_suburb,
+                "stop_landmark": self.stop_landmark,
+                "route_type": self.route_type,
+            },
+        })
+    }
+}
+
+impl RouteDetails {
+    /// Export the route as a GeoJSON `FeatureCollection`
+    ///
+    /// There's a `MultiLineString` feature for each geopath (i.e. direction of travel),
+    /// so this is empty unless the route was requested with `include_geopath`.
+    pub fn to_geojson(&self) -> serde_json::Value {
+        let features = self.geopath.iter().flatten().map(|geopath| {
+            let lines: Vec<Vec<_>> = geopath
+                .paths
+                .iter()
+                .map(|path| path.0.iter().map(geojson_position).collect())
+                .collect();
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "MultiLineString",
+                    "coordinates": lines,
+                },
+                "properties": {
+                    "route_id": self.route_id,
+                    "route_name": self.route_name,
+                    "route_number": self.route_number,
+                    "route_type": self.route_type,
+                    "direction_id": geopath.direction_id,
+                    "valid_from": geopath.valid_from,
+                    "valid_to": geopath.valid_to,
+                },
+            })
+        });
+        geojson_collection(features)
+    }
+}
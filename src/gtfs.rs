@@ -0,0 +1,464 @@
+use crate::*;
+use futures_util::StreamExt;
+use std::collections::BTreeSet;
+use time::Date;
+
+/// Maximum number of requests made at once by [PTV::gtfs_feed]
+const GTFS_CONCURRENCY: usize = 4;
+
+/// Options for [PTV::gtfs_feed]
+#[derive(Clone, Debug)]
+pub struct GtfsParams {
+    /// First service date to include (Melbourne time)
+    pub start: Date,
+    /// Last service date to include (Melbourne time)
+    pub end: Date,
+    /// Filter by route_type (default = all)
+    pub route_types: Option<Vec<RouteType>>,
+}
+
+/// A trip in a [GtfsFeed]
+#[derive(Clone, Debug)]
+struct GtfsTrip {
+    route: Route,
+    service_date: Date,
+    headsign: String,
+    /// Stop, departure time in seconds after midnight of the service date, and stop sequence
+    stop_times: Vec<(Stop, i64, u32)>,
+}
+
+/// A GTFS static feed, built from routes and stopping patterns
+///
+/// Times are Melbourne local time, and the API only provides departure times, so they're also
+/// used as arrival times.
+#[derive(Clone, Debug)]
+pub struct GtfsFeed {
+    start: Date,
+    end: Date,
+    routes: BTreeMap<Route, RouteDetails>,
+    stops: BTreeMap<Stop, StopDetails>,
+    trips: BTreeMap<String, GtfsTrip>,
+}
+
+/// GTFS `route_type` for a PTV route type
+fn gtfs_route_type(route_type: RouteType) -> i32 {
+    match route_type {
+        RouteType::TRAM => 0,
+        RouteType::TRAIN | RouteType::VLINE => 2,
+        _ => 3,
+    }
+}
+
+/// GTFS identifier for a route, the PTV GTFS identifier where there is one
+pub(crate) fn gtfs_route_id(route: &Route, route_gtfs_id: Option<&str>) -> String {
+    match route_gtfs_id {
+        Some(id) if !id.is_empty() => id.to_string(),
+        _ => format!("ptv-{}-{}", route.route_type.value(), route.id),
+    }
+}
+
+/// GTFS identifier for a stop (stop identifiers are only unique within a route type)
+pub(crate) fn gtfs_stop_id(stop: &Stop) -> String {
+    format!("{}-{}", stop.route_type.value(), stop.id)
+}
+
+/// GTFS `stop_sequence` of a departure, its `departure_sequence` when that's positive
+///
+/// Shared by [GtfsFeed] and GTFS-Realtime trip updates so they match up.
+pub(crate) fn gtfs_stop_sequence(departure: &DepartureDetails) -> Option<u32> {
+    u32::try_from(departure.departure_sequence)
+        .ok()
+        .filter(|&sequence| sequence > 0)
+}
+
+/// GTFS identifier for a trip, a run on a service date
+pub(crate) fn gtfs_trip_id(run: &Run, service_date: Date) -> String {
+    format!(
+        "{}-{}-{}",
+        run.route_type.value(),
+        run.id,
+        gtfs_date(service_date)
+    )
+}
+
+/// GTFS date (`YYYYMMDD`)
+pub(crate) fn gtfs_date(date: Date) -> String {
+    format!(
+        "{:04}{:02}{:02}",
+        date.year(),
+        date.month() as u8,
+        date.day()
+    )
+}
+
+/// GTFS time (`HH:MM:SS`, which can be past 24:00:00 for trips running after midnight)
+fn gtfs_time(seconds: i64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Internal helper to format a CSV line, quoting fields where needed
+fn csv_line(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    fields.join(",") + "\r\n"
+}
+
+/// Mapping between GTFS stop identifiers and PTV stops, learned from responses
+#[derive(Clone, Debug, Default)]
+pub struct GtfsStopMap {
+    stops: BTreeMap<(RouteType, GtfsStopId), Stop>,
+    gtfs_stop_ids: BTreeMap<Stop, GtfsStopId>,
+}
+
+impl GtfsStopMap {
+    /// Record that a GTFS stop identifier refers to a PTV stop
+    pub fn insert(&mut self, gtfs_stop_id: GtfsStopId, stop: Stop) {
+        self.stops
+            .insert((stop.route_type, gtfs_stop_id.clone()), stop.clone());
+        self.gtfs_stop_ids.insert(stop, gtfs_stop_id);
+    }
+    /// Learn from a response to [PTV::departures_gtfs], returning the PTV stop if there were any departures
+    pub fn add_departures(
+        &mut self,
+        route_type: RouteType,
+        gtfs_stop_id: &GtfsStopId,
+        response: &DeparturesResponse,
+    ) -> Option<Stop> {
+        let departure = response.departures.first()?;
+        let stop = Stop {
+            route_type,
+            id: departure.stop_id.clone(),
+        };
+        self.insert(gtfs_stop_id.clone(), stop.clone());
+        Some(stop)
+    }
+    /// Learn from a response to [PTV::search_gtfs_stop], returning the PTV stop if exactly one matched
+    pub fn add_search(
+        &mut self,
+        gtfs_stop_id: &GtfsStopId,
+        response: &SearchResponse,
+    ) -> Option<Stop> {
+        let [details] = response.stops.as_slice() else {
+            return None;
+        };
+        let stop = details.stop();
+        self.insert(gtfs_stop_id.clone(), stop.clone());
+        Some(stop)
+    }
+    /// The PTV stop with a GTFS stop identifier
+    pub fn stop(&self, route_type: RouteType, gtfs_stop_id: &GtfsStopId) -> Option<&Stop> {
+        self.stops.get(&(route_type, gtfs_stop_id.clone()))
+    }
+    /// The GTFS stop identifier of a PTV stop
+    pub fn gtfs_stop_id(&self, stop: &Stop) -> Option<&GtfsStopId> {
+        self.gtfs_stop_ids.get(stop)
+    }
+    /// Number of stops mapped
+    pub fn len(&self) -> usize {
+        self.gtfs_stop_ids.len()
+    }
+    /// Whether no stops are mapped
+    pub fn is_empty(&self) -> bool {
+        self.gtfs_stop_ids.is_empty()
+    }
+}
+
+impl GtfsFeed {
+    /// An empty feed for service dates from `start` to `end` (inclusive)
+    pub fn new(start: Date, end: Date) -> Self {
+        Self {
+            start,
+            end,
+            routes: BTreeMap::new(),
+            stops: BTreeMap::new(),
+            trips: BTreeMap::new(),
+        }
+    }
+
+    /// Add a route, even if none of its trips are added
+    pub fn add_route(&mut self, route: &RouteDetails) {
+        self.routes.insert(route.route(), route.clone());
+    }
+
+    /// Add a trip from the stopping pattern of a run
+    ///
+    /// The pattern needs `Stop` expanded. Trips with a service date (the Melbourne date of the
+    /// first departure) outside the feed's dates are skipped. Returns whether the trip was added.
+    pub fn add_pattern(&mut self, run: &Run, pattern: &PatternResponse) -> bool {
+        let mut departures: Vec<_> = pattern.departures.iter().collect();
+        departures.sort_by_key(|d| d.departure_sequence);
+        let Some(first) = departures.first() else {
+            return false;
+        };
+        let service_date = datetime::to_melbourne(first.scheduled_departure_utc).date();
+        if service_date < self.start || service_date > self.end {
+            return false;
+        }
+        let midnight = datetime::melbourne_midnight(service_date);
+
+        let route = Route {
+            route_type: run.route_type,
+            id: first.route_id.clone(),
+        };
+        if let Some(details) = pattern.routes.get(&route.id) {
+            self.routes
+                .entry(route.clone())
+                .or_insert_with(|| details.clone());
+        }
+        // Sequences must increase along the trip, otherwise number the stops in order
+        let sequences: Option<Vec<u32>> =
+            departures.iter().map(|d| gtfs_stop_sequence(d)).collect();
+        let sequences = match sequences {
+            Some(sequences) if sequences.windows(2).all(|pair| pair[0] < pair[1]) => sequences,
+            _ => (1..=departures.len() as u32).collect(),
+        };
+        let mut stop_times = Vec::new();
+        for (departure, sequence) in departures.into_iter().zip(sequences) {
+            let stop = Stop {
+                route_type: run.route_type,
+                id: departure.stop_id.clone(),
+            };
+            if let Some(details) = pattern.stops.get(&stop.id) {
+                self.stops
+                    .entry(stop.clone())
+                    .or_insert_with(|| details.clone());
+            }
+            let seconds = (departure.scheduled_departure_utc - midnight).whole_seconds();
+            stop_times.push((stop, seconds, sequence));
+        }
+
+        let headsign = pattern
+            .runs
+            .get(&run.id)
+            .map(|r| r.destination_name.clone())
+            .unwrap_or_default();
+        let trip_id = gtfs_trip_id(run, service_date);
+        self.trips.insert(
+            trip_id,
+            GtfsTrip {
+                route,
+                service_date,
+                headsign,
+                stop_times,
+            },
+        );
+        true
+    }
+
+    /// Number of trips in the feed
+    pub fn trip_count(&self) -> usize {
+        self.trips.len()
+    }
+
+    /// Contents of each file in the feed, as (file name, CSV)
+    pub fn files(&self) -> Vec<(&'static str, String)> {
+        let agency = csv_line(&[
+            "agency_id",
+            "agency_name",
+            "agency_url",
+            "agency_timezone",
+            "agency_lang",
+        ]) + &csv_line(&[
+            "PTV",
+            "Public Transport Victoria",
+            "https://www.ptv.vic.gov.au",
+            "Australia/Melbourne",
+            "en",
+        ]);
+
+        let mut routes = csv_line(&[
+            "route_id",
+            "agency_id",
+            "route_short_name",
+            "route_long_name",
+            "route_type",
+        ]);
+        for route in self.routes.values() {
+            routes += &csv_line(&[
+                &gtfs_route_id(&route.route(), route.route_gtfs_id.as_deref()),
+                "PTV",
+                &route.route_number,
+                &route.route_name,
+                &gtfs_route_type(route.route_type).to_string(),
+            ]);
+        }
+
+        let mut stops = csv_line(&["stop_id", "stop_name", "stop_lat", "stop_lon"]);
+        for (stop, details) in &self.stops {
+            stops += &csv_line(&[
+                &gtfs_stop_id(stop),
+                &details.stop_name,
+                &details.stop_latitude.to_string(),
+                &details.stop_longitude.to_string(),
+            ]);
+        }
+
+        let mut trips = csv_line(&["route_id", "service_id", "trip_id", "trip_headsign"]);
+        let mut stop_times = csv_line(&[
+            "trip_id",
+            "arrival_time",
+            "departure_time",
+            "stop_id",
+            "stop_sequence",
+        ]);
+        let mut service_dates = BTreeSet::new();
+        for (trip_id, trip) in &self.trips {
+            let route_gtfs_id = self
+                .routes
+                .get(&trip.route)
+                .and_then(|route| route.route_gtfs_id.as_deref());
+            let route_id = gtfs_route_id(&trip.route, route_gtfs_id);
+            let service_id = gtfs_date(trip.service_date);
+            trips += &csv_line(&[&route_id, &service_id, trip_id, &trip.headsign]);
+            service_dates.insert(service_id);
+            for (stop, seconds, sequence) in &trip.stop_times {
+                let time = gtfs_time(*seconds);
+                stop_times += &csv_line(&[
+                    trip_id,
+                    &time,
+                    &time,
+                    &gtfs_stop_id(stop),
+                    &sequence.to_string(),
+                ]);
+            }
+        }
+
+        // Each service date has its own service_id, so everything is an exception
+        let mut calendar_dates = csv_line(&["service_id", "date", "exception_type"]);
+        for date in &service_dates {
+            calendar_dates += &csv_line(&[date, date, "1"]);
+        }
+
+        vec![
+            ("agency.txt", agency),
+            ("routes.txt", routes),
+            ("stops.txt", stops),
+            ("trips.txt", trips),
+            ("stop_times.txt", stop_times),
+            ("calendar_dates.txt", calendar_dates),
+        ]
+    }
+
+    /// Write the feed as a GTFS zip file
+    #[cfg(feature = "gtfs")]
+    pub fn write_zip(&self, writer: impl std::io::Write + std::io::Seek) -> Result<()> {
+        use std::io::Write;
+        let error = |e: &dyn std::fmt::Display| Error::Other(format!("Failed to write GTFS: {e}"));
+        let mut zip = zip::ZipWriter::new(writer);
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, contents) in self.files() {
+            zip.start_file(name, options).map_err(|e| error(&e))?;
+            zip.write_all(contents.as_bytes()).map_err(|e| error(&e))?;
+        }
+        zip.finish().map_err(|e| error(&e))?;
+        Ok(())
+    }
+}
+
+/// A request which failed while building a feed, see [GtfsFeedResponse::errors]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GtfsRequest {
+    /// The runs of a route on a service date
+    Runs(Route, Date),
+    /// The stopping pattern of a run on a service date
+    Pattern(Run, Date),
+}
+
+/// Response from [PTV::gtfs_feed]
+#[derive(Clone, Debug)]
+pub struct GtfsFeedResponse {
+    /// The feed, without the runs and patterns which couldn't be fetched
+    pub feed: GtfsFeed,
+    /// Requests which failed, with the reason
+    pub errors: Vec<(GtfsRequest, Error)>,
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// Build a GTFS static feed from the routes, runs and stopping patterns for a range of service dates
+    ///
+    /// This requests the runs of every route for every day, then the stopping pattern of every run,
+    /// so it's slow for more than a few routes. Use `route_types` to limit it. Only failing to
+    /// fetch the routes fails the whole feed, runs and patterns which can't be fetched are left
+    /// out and reported in [GtfsFeedResponse::errors].
+    pub async fn gtfs_feed(&self, params: GtfsParams) -> Result<GtfsFeedResponse> {
+        let mut feed = GtfsFeed::new(params.start, params.end);
+        let mut errors = Vec::new();
+        let routes_params = RoutesParams {
+            route_types: params.route_types.clone(),
+            ..Default::default()
+        };
+        let routes = self.routes(routes_params).await?.routes;
+        for route in &routes {
+            feed.add_route(route);
+        }
+        let routes: Vec<Route> = routes.iter().map(RouteDetails::route).collect();
+
+        let mut date = params.start;
+        while date <= params.end {
+            let date_utc = Some(datetime::to_string(datetime::melbourne_midnight(date)));
+            let runs_params = RunsParams {
+                date_utc: date_utc.clone(),
+                ..Default::default()
+            };
+            let results: Vec<_> = futures_util::stream::iter(&routes)
+                .map(|route| self.runs_for_route(route, runs_params.clone()))
+                .buffered(GTFS_CONCURRENCY)
+                .collect()
+                .await;
+            let mut runs = Vec::new();
+            for (route, result) in routes.iter().zip(results) {
+                match result {
+                    Ok(response) => runs.extend(response.runs.into_iter().map(|run| Run {
+                        route_type: run.route_type,
+                        id: run.run_ref,
+                    })),
+                    Err(error) => errors.push((GtfsRequest::Runs(route.clone(), date), error)),
+                }
+            }
+            runs.sort();
+            runs.dedup();
+
+            let pattern_params = PatternParams {
+                date_utc,
+                expand: Some(vec![
+                    ResponseExpand::Stop,
+                    ResponseExpand::Route,
+                    ResponseExpand::Run,
+                ]),
+                ..Default::default()
+            };
+            let patterns: Vec<_> = futures_util::stream::iter(&runs)
+                .map(|run| self.pattern(run, pattern_params.clone()))
+                .buffered(GTFS_CONCURRENCY)
+                .collect()
+                .await;
+            for (run, pattern) in runs.iter().zip(patterns) {
+                match pattern {
+                    Ok(pattern) => {
+                        feed.add_pattern(run, &pattern);
+                    }
+                    Err(error) => errors.push((GtfsRequest::Pattern(run.clone(), date), error)),
+                }
+            }
+
+            let Some(next) = date.next_day() else {
+                break;
+            };
+            date = next;
+        }
+        Ok(GtfsFeedResponse { feed, errors })
+    }
+}
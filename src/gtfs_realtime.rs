@@ -0,0 +1,621 @@
+//! GTFS-Realtime feeds built from departures and disruptions
+//!
+//! The messages are a hand written subset of
+//! [gtfs-realtime.proto](https://gtfs.org/realtime/proto/), covering trip updates, vehicle
+//! positions and alerts. Identifiers match those of [GtfsFeed](crate::GtfsFeed).
+use crate::gtfs::{gtfs_date, gtfs_route_id, gtfs_stop_id, gtfs_stop_sequence, gtfs_trip_id};
+use crate::{datetime, DeparturesResponse, DisruptionDetails, Route, Run, Stop};
+use prost::Message;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use time::format_description::well_known::Iso8601;
+use time::OffsetDateTime;
+
+/// Version of the GTFS-Realtime specification these messages follow
+pub const GTFS_REALTIME_VERSION: &str = "2.0";
+
+/// The contents of a feed message
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct FeedMessage {
+    /// Metadata about this feed and feed message
+    #[prost(message, required, tag = "1")]
+    pub header: FeedHeader,
+    /// Contents of the feed
+    #[prost(message, repeated, tag = "2")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entity: Vec<FeedEntity>,
+}
+
+/// Metadata about a feed, included in feed messages
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct FeedHeader {
+    /// Version of the feed specification
+    #[prost(string, required, tag = "1")]
+    pub gtfs_realtime_version: String,
+    /// Whether this is the full dataset, see [Incrementality]
+    #[prost(enumeration = "Incrementality", optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incrementality: Option<i32>,
+    /// When the content of this feed was created (POSIX time)
+    #[prost(uint64, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+/// Whether a feed is a full dataset or differential
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Incrementality {
+    FullDataset = 0,
+    Differential = 1,
+}
+
+/// A definition (or update) of an entity in the transit feed
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct FeedEntity {
+    /// Unique identifier of the entity within the feed
+    #[prost(string, required, tag = "1")]
+    pub id: String,
+    /// Whether this entity is to be deleted (only for differential feeds)
+    #[prost(bool, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_deleted: Option<bool>,
+    /// Real-time progress of a trip
+    #[prost(message, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip_update: Option<TripUpdate>,
+    /// Real-time position of a vehicle
+    #[prost(message, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vehicle: Option<VehiclePosition>,
+    /// An alert about an incident in the network
+    #[prost(message, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<Alert>,
+}
+
+/// Real-time update of the progress of a vehicle along a trip
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct TripUpdate {
+    /// The trip this update applies to
+    #[prost(message, required, tag = "1")]
+    pub trip: TripDescriptor,
+    /// Updates to stop times of the trip, ordered by stop sequence
+    #[prost(message, repeated, tag = "2")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_time_update: Vec<StopTimeUpdate>,
+    /// Information about the vehicle serving the trip
+    #[prost(message, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vehicle: Option<VehicleDescriptor>,
+    /// When the real-time progress was measured (POSIX time)
+    #[prost(uint64, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+/// Real-time update for a stop of a trip
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct StopTimeUpdate {
+    /// Stop sequence, as in `stop_times.txt`
+    #[prost(uint32, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequence: Option<u32>,
+    /// Arrival at the stop
+    #[prost(message, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arrival: Option<StopTimeEvent>,
+    /// Departure from the stop
+    #[prost(message, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub departure: Option<StopTimeEvent>,
+    /// Stop identifier, as in `stops.txt`
+    #[prost(string, optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_id: Option<String>,
+    /// See [StopScheduleRelationship]
+    #[prost(enumeration = "StopScheduleRelationship", optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule_relationship: Option<i32>,
+}
+
+/// Relationship between a stop time update and the static schedule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum StopScheduleRelationship {
+    Scheduled = 0,
+    Skipped = 1,
+    NoData = 2,
+}
+
+/// Timing of a single predicted event (arrival or departure)
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct StopTimeEvent {
+    /// Delay (in seconds), positive meaning the vehicle is late
+    #[prost(int32, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<i32>,
+    /// Event as absolute time (POSIX time)
+    #[prost(int64, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<i64>,
+}
+
+/// Identifies an instance of a GTFS trip
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct TripDescriptor {
+    /// Trip identifier, as in `trips.txt`
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip_id: Option<String>,
+    /// Start date of the trip instance (`YYYYMMDD`)
+    #[prost(string, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<String>,
+    /// See [TripScheduleRelationship]
+    #[prost(enumeration = "TripScheduleRelationship", optional, tag = "4")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule_relationship: Option<i32>,
+    /// Route identifier, as in `routes.txt`
+    #[prost(string, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_id: Option<String>,
+}
+
+/// Relationship between a trip and the static schedule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum TripScheduleRelationship {
+    Scheduled = 0,
+    Added = 1,
+    Unscheduled = 2,
+    Canceled = 3,
+}
+
+/// Identification information for the vehicle performing a trip
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct VehicleDescriptor {
+    /// Internal system identification of the vehicle
+    #[prost(string, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// User visible label
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Real-time positioning information for a vehicle
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct VehiclePosition {
+    /// The trip the vehicle is serving
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trip: Option<TripDescriptor>,
+    /// Current position of the vehicle
+    #[prost(message, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
+    /// When the position was measured (POSIX time)
+    #[prost(uint64, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// Information about the vehicle
+    #[prost(message, optional, tag = "8")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vehicle: Option<VehicleDescriptor>,
+}
+
+/// A geographic position of a vehicle
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct Position {
+    /// Degrees North, in the WGS-84 coordinate system
+    #[prost(float, required, tag = "1")]
+    pub latitude: f32,
+    /// Degrees East, in the WGS-84 coordinate system
+    #[prost(float, required, tag = "2")]
+    pub longitude: f32,
+    /// Bearing, in degrees, clockwise from True North
+    #[prost(float, optional, tag = "3")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bearing: Option<f32>,
+}
+
+/// An alert, indicating some sort of incident in the network
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct Alert {
+    /// Times when the alert should be shown
+    #[prost(message, repeated, tag = "1")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub active_period: Vec<TimeRange>,
+    /// Entities whose users should be notified of this alert
+    #[prost(message, repeated, tag = "5")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub informed_entity: Vec<EntitySelector>,
+    /// See [Cause]
+    #[prost(enumeration = "Cause", optional, tag = "6")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<i32>,
+    /// See [Effect]
+    #[prost(enumeration = "Effect", optional, tag = "7")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effect: Option<i32>,
+    /// URL with additional information
+    #[prost(message, optional, tag = "8")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<TranslatedString>,
+    /// Header for the alert
+    #[prost(message, optional, tag = "10")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header_text: Option<TranslatedString>,
+    /// Description for the alert
+    #[prost(message, optional, tag = "11")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_text: Option<TranslatedString>,
+}
+
+/// Cause of an alert
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Cause {
+    UnknownCause = 1,
+    OtherCause = 2,
+    TechnicalProblem = 3,
+    Strike = 4,
+    Demonstration = 5,
+    Accident = 6,
+    Holiday = 7,
+    Weather = 8,
+    Maintenance = 9,
+    Construction = 10,
+    PoliceActivity = 11,
+    MedicalEmergency = 12,
+}
+
+/// Effect of an alert on the affected entities
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum Effect {
+    NoService = 1,
+    ReducedService = 2,
+    SignificantDelays = 3,
+    Detour = 4,
+    AdditionalService = 5,
+    ModifiedService = 6,
+    OtherEffect = 7,
+    UnknownEffect = 8,
+    StopMoved = 9,
+}
+
+/// A time interval (POSIX times), open ended when either is missing
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct TimeRange {
+    /// Start time
+    #[prost(uint64, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<u64>,
+    /// End time
+    #[prost(uint64, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<u64>,
+}
+
+/// A selector for an entity in a GTFS feed
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct EntitySelector {
+    /// Route identifier, as in `routes.txt`
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_id: Option<String>,
+    /// Stop identifier, as in `stops.txt`
+    #[prost(string, optional, tag = "5")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_id: Option<String>,
+}
+
+/// Text in one or more languages
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct TranslatedString {
+    /// At least one translation
+    #[prost(message, repeated, tag = "1")]
+    pub translation: Vec<Translation>,
+}
+
+/// Text in a single language
+#[derive(Clone, PartialEq, Message, Serialize)]
+pub struct Translation {
+    /// The text
+    #[prost(string, required, tag = "1")]
+    pub text: String,
+    /// BCP-47 language code
+    #[prost(string, optional, tag = "2")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl TranslatedString {
+    fn english(text: &str) -> Option<Self> {
+        if text.is_empty() {
+            return None;
+        }
+        Some(Self {
+            translation: vec![Translation {
+                text: text.to_string(),
+                language: Some("en".to_string()),
+            }],
+        })
+    }
+}
+
+impl FeedMessage {
+    /// An empty full dataset feed message
+    pub fn new(timestamp: OffsetDateTime) -> Self {
+        Self {
+            header: FeedHeader {
+                gtfs_realtime_version: GTFS_REALTIME_VERSION.to_string(),
+                incrementality: Some(Incrementality::FullDataset as i32),
+                timestamp: Some(timestamp.unix_timestamp() as u64),
+            },
+            entity: Vec::new(),
+        }
+    }
+    /// Encode as protobuf binary
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode_to_vec()
+    }
+    /// Encode as JSON (using the protobuf field names)
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Failed to serialize feed message")
+    }
+}
+
+/// Internal helper to parse the date/time strings the API doesn't otherwise parse
+fn posix_time(text: Option<&str>) -> Option<u64> {
+    let datetime = OffsetDateTime::parse(text?, &Iso8601::DEFAULT).ok()?;
+    Some(datetime.unix_timestamp() as u64)
+}
+
+/// Internal helper to describe the trip of a run, the service date being the date of `first_departure`
+fn trip_descriptor(
+    response: &DeparturesResponse,
+    run: &Run,
+    route: &Route,
+    first_departure: OffsetDateTime,
+) -> TripDescriptor {
+    let service_date = datetime::to_melbourne(first_departure).date();
+    let route_gtfs_id = response
+        .routes
+        .get(&route.id)
+        .and_then(|r| r.route_gtfs_id.as_deref());
+    let cancelled = response
+        .runs
+        .get(&run.id)
+        .is_some_and(|r| r.status.eq_ignore_ascii_case("cancelled"));
+    let schedule_relationship = if cancelled {
+        TripScheduleRelationship::Canceled
+    } else {
+        TripScheduleRelationship::Scheduled
+    };
+    TripDescriptor {
+        trip_id: Some(gtfs_trip_id(run, service_date)),
+        start_date: Some(gtfs_date(service_date)),
+        schedule_relationship: Some(schedule_relationship as i32),
+        route_id: Some(gtfs_route_id(route, route_gtfs_id)),
+    }
+}
+
+fn vehicle_descriptor(run: &crate::RunDetails) -> Option<VehicleDescriptor> {
+    let descriptor = run.vehicle_descriptor.as_ref()?;
+    Some(VehicleDescriptor {
+        id: descriptor.id.clone(),
+        label: descriptor.description.clone(),
+    })
+}
+
+/// Trip updates for the runs in a departures response
+///
+/// Each run with a real-time estimate (or which is cancelled) becomes a trip update, with the
+/// estimates as stop time update delays. The response needs `Run` expanded. The trip's start date
+/// is taken from the earliest departure of the run in the response, so only matches
+/// [GtfsFeed](crate::GtfsFeed) if that's on the same day as the run started.
+pub fn trip_updates(response: &DeparturesResponse, timestamp: OffsetDateTime) -> FeedMessage {
+    let mut runs: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for departure in &response.departures {
+        runs.entry(&departure.run_ref).or_default().push(departure);
+    }
+
+    let mut message = FeedMessage::new(timestamp);
+    for (run_ref, mut departures) in runs {
+        let Some(details) = response.runs.get(run_ref) else {
+            continue;
+        };
+        departures.sort_by_key(|d| d.departure_sequence);
+        let run = Run {
+            route_type: details.route_type,
+            id: run_ref.clone(),
+        };
+        let route = Route {
+            route_type: details.route_type,
+            id: details.route_id.clone(),
+        };
+        let trip = trip_descriptor(
+            response,
+            &run,
+            &route,
+            departures[0].scheduled_departure_utc,
+        );
+
+        let stop_time_update: Vec<_> = departures
+            .iter()
+            .filter_map(|departure| {
+                let estimated = departure.estimated_departure_utc?;
+                let stop = Stop {
+                    route_type: details.route_type,
+                    id: departure.stop_id.clone(),
+                };
+                let delay = (estimated - departure.scheduled_departure_utc).whole_seconds();
+                Some(StopTimeUpdate {
+                    stop_sequence: gtfs_stop_sequence(departure),
+                    arrival: None,
+                    departure: Some(StopTimeEvent {
+                        delay: Some(delay as i32),
+                        time: Some(estimated.unix_timestamp()),
+                    }),
+                    stop_id: Some(gtfs_stop_id(&stop)),
+                    schedule_relationship: Some(StopScheduleRelationship::Scheduled as i32),
+                })
+            })
+            .collect();
+        let cancelled =
+            trip.schedule_relationship == Some(TripScheduleRelationship::Canceled as i32);
+        if stop_time_update.is_empty() && !cancelled {
+            continue;
+        }
+
+        message.entity.push(FeedEntity {
+            id: format!("trip-{}", trip.trip_id.as_deref().unwrap_or_default()),
+            trip_update: Some(TripUpdate {
+                trip,
+                stop_time_update,
+                vehicle: vehicle_descriptor(details),
+                timestamp: None,
+            }),
+            ..Default::default()
+        });
+    }
+    message
+}
+
+/// Vehicle positions for the runs in a departures response
+///
+/// Only runs with a known location are included, which needs `Run` and `VehiclePosition` expanded.
+pub fn vehicle_positions(response: &DeparturesResponse, timestamp: OffsetDateTime) -> FeedMessage {
+    let mut message = FeedMessage::new(timestamp);
+    for (run_ref, details) in &response.runs {
+        let Some(position) = &details.vehicle_position else {
+            continue;
+        };
+        let Some(location) = position.location() else {
+            continue;
+        };
+        let run = Run {
+            route_type: details.route_type,
+            id: run_ref.clone(),
+        };
+        let route = Route {
+            route_type: details.route_type,
+            id: details.route_id.clone(),
+        };
+        let first_departure = response
+            .departures
+            .iter()
+            .filter(|d| &d.run_ref == run_ref)
+            .map(|d| d.scheduled_departure_utc)
+            .min()
+            .unwrap_or(timestamp);
+        let trip = trip_descriptor(response, &run, &route, first_departure);
+
+        message.entity.push(FeedEntity {
+            id: format!("vehicle-{}", trip.trip_id.as_deref().unwrap_or_default()),
+            vehicle: Some(VehiclePosition {
+                trip: Some(trip),
+                position: Some(Position {
+                    latitude: location.latitude as f32,
+                    longitude: location.longitude as f32,
+                    bearing: position.bearing.map(|bearing| bearing as f32),
+                }),
+                timestamp: posix_time(Some(&position.datetime_utc)),
+                vehicle: vehicle_descriptor(details),
+            }),
+            ..Default::default()
+        });
+    }
+    message
+}
+
+/// Internal helper to guess the cause and effect of a disruption from its type (e.g. "Planned Works")
+fn cause_and_effect(disruption: &DisruptionDetails) -> (Cause, Effect) {
+    let disruption_type = disruption.disruption_type.to_lowercase();
+    let cause = if disruption_type.contains("works") {
+        Cause::Maintenance
+    } else {
+        Cause::UnknownCause
+    };
+    let effect = if disruption_type.contains("delay") {
+        Effect::SignificantDelays
+    } else if disruption_type.contains("suspend") || disruption_type.contains("closure") {
+        Effect::NoService
+    } else if disruption_type.contains("works") || disruption_type.contains("alteration") {
+        Effect::ModifiedService
+    } else {
+        Effect::UnknownEffect
+    };
+    (cause, effect)
+}
+
+/// Alerts for disruptions
+///
+/// Affected routes and stops become informed entities. Stops only have an identifier in
+/// disruptions, so they're assumed to be of the same route type(s) as the affected routes.
+/// Disruptions which only affect stops can't be given identifiers, so they're left out.
+pub fn alerts<'a>(
+    disruptions: impl IntoIterator<Item = &'a DisruptionDetails>,
+    timestamp: OffsetDateTime,
+) -> FeedMessage {
+    let mut message = FeedMessage::new(timestamp);
+    for disruption in disruptions {
+        let routes = disruption.routes.as_deref().unwrap_or_default();
+        let mut informed_entity: Vec<_> = routes
+            .iter()
+            .map(|r| {
+                let route = Route {
+                    route_type: r.route_type,
+                    id: r.route_id.clone(),
+                };
+                EntitySelector {
+                    route_id: Some(gtfs_route_id(&route, r.route_gtfs_id.as_deref())),
+                    stop_id: None,
+                }
+            })
+            .collect();
+        let mut route_types: Vec<_> = routes.iter().map(|r| r.route_type).collect();
+        route_types.sort();
+        route_types.dedup();
+        for stop in disruption.stops.as_deref().unwrap_or_default() {
+            for &route_type in &route_types {
+                let stop = Stop {
+                    route_type,
+                    id: stop.stop_id.clone(),
+                };
+                informed_entity.push(EntitySelector {
+                    route_id: None,
+                    stop_id: Some(gtfs_stop_id(&stop)),
+                });
+            }
+        }
+
+        if informed_entity.is_empty() {
+            continue;
+        }
+
+        let active_period = TimeRange {
+            start: posix_time(disruption.from_date.as_deref()),
+            end: posix_time(disruption.to_date.as_deref()),
+        };
+        let (cause, effect) = cause_and_effect(disruption);
+        message.entity.push(FeedEntity {
+            id: format!("alert-{}", disruption.disruption_id),
+            alert: Some(Alert {
+                active_period: vec![active_period],
+                informed_entity,
+                cause: Some(cause as i32),
+                effect: Some(effect as i32),
+                url: TranslatedString::english(&disruption.url),
+                header_text: TranslatedString::english(&disruption.title),
+                description_text: TranslatedString::english(&disruption.description),
+            }),
+            ..Default::default()
+        });
+    }
+    message
+}
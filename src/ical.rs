@@ -0,0 +1,205 @@
+use crate::*;
+use time::format_description::well_known::Iso8601;
+
+/// An event in a [Calendar]
+#[derive(Clone, Debug)]
+pub struct CalendarEvent {
+    /// Globally unique and stable identifier of the event
+    pub uid: String,
+    /// When the event starts
+    pub start: OffsetDateTime,
+    /// When the event ends (if known)
+    pub end: Option<OffsetDateTime>,
+    /// Short summary of the event
+    pub summary: String,
+    /// Longer description of the event
+    pub description: Option<String>,
+    /// Where the event happens
+    pub location: Option<String>,
+    /// Coordinates of where the event happens
+    pub geo: Option<LatLon>,
+    /// Web page with more information
+    pub url: Option<String>,
+}
+
+/// An RFC 5545 iCalendar of departures and disruptions
+///
+/// Use [Calendar::add_departures] and [Calendar::add_disruptions], then format it with `to_string()`
+/// to get the contents of an `.ics` file.
+#[derive(Clone, Debug)]
+pub struct Calendar {
+    /// Name of the calendar, shown by calendar applications
+    pub name: Option<String>,
+    /// When the calendar was generated (used for `DTSTAMP`)
+    pub timestamp: OffsetDateTime,
+    /// Events in the calendar
+    pub events: Vec<CalendarEvent>,
+}
+
+/// Domain used to make UIDs globally unique
+const UID_DOMAIN: &str = "timetableapi.ptv.vic.gov.au";
+
+impl Calendar {
+    /// An empty calendar
+    pub fn new(name: Option<String>, timestamp: OffsetDateTime) -> Self {
+        Self {
+            name,
+            timestamp,
+            events: Vec::new(),
+        }
+    }
+
+    /// Add an event for each departure in a response
+    ///
+    /// Expand `Stop`, `Route` and `Run` to get the location, route name and destination.
+    pub fn add_departures(&mut self, response: &DeparturesResponse) {
+        for departure in response.resolved() {
+            let summary = match (departure.route(), departure.destination()) {
+                (Some(route), Some(destination)) => {
+                    format!("{} to {destination}", route.route_name)
+                }
+                (Some(route), None) => route.route_name.clone(),
+                (None, Some(destination)) => format!("To {destination}"),
+                (None, None) => "Departure".to_string(),
+            };
+            let mut description = Vec::new();
+            if let Some(platform) = departure.platform() {
+                description.push(format!("Platform {platform}"));
+            }
+            // Less than a minute either way is on time
+            match departure.delay().map(|delay| delay.whole_minutes()) {
+                Some(minutes) if minutes > 0 => description.push(format!("Delayed {minutes} min")),
+                Some(minutes) if minutes < 0 => description.push(format!("Early {} min", -minutes)),
+                _ => {}
+            }
+            let stop = departure.stop();
+            self.events.push(CalendarEvent {
+                uid: format!(
+                    "departure-{}-{}-{}@{UID_DOMAIN}",
+                    departure.run_ref, departure.stop_id, departure.departure_sequence
+                ),
+                start: departure.departure_time(),
+                end: None,
+                summary,
+                description: (!description.is_empty()).then(|| description.join("\n")),
+                location: stop.map(|stop| stop.stop_name.clone()),
+                geo: stop.map(StopDetails::location),
+                url: None,
+            });
+        }
+    }
+
+    /// Add an event for each disruption with a start date (i.e. planned works), returning how many were added
+    pub fn add_disruptions<'a>(
+        &mut self,
+        disruptions: impl IntoIterator<Item = &'a DisruptionDetails>,
+    ) -> usize {
+        let parse = |date: Option<&str>| OffsetDateTime::parse(date?, &Iso8601::DEFAULT).ok();
+        let mut added = 0;
+        for disruption in disruptions {
+            let Some(start) = parse(disruption.from_date.as_deref()) else {
+                continue;
+            };
+            let stops: Vec<_> = disruption
+                .stops
+                .iter()
+                .flatten()
+                .map(|stop| stop.stop_name.as_str())
+                .collect();
+            self.events.push(CalendarEvent {
+                uid: format!("disruption-{}@{UID_DOMAIN}", disruption.disruption_id),
+                start,
+                end: parse(disruption.to_date.as_deref()),
+                summary: disruption.title.clone(),
+                description: Some(disruption.description.clone()).filter(|d| !d.is_empty()),
+                location: (!stops.is_empty()).then(|| stops.join(", ")),
+                geo: None,
+                url: Some(disruption.url.clone()).filter(|url| !url.is_empty()),
+            });
+            added += 1;
+        }
+        added
+    }
+}
+
+/// Internal helper to format a UTC date-time (`YYYYMMDDTHHMMSSZ`)
+fn ical_datetime(datetime: OffsetDateTime) -> String {
+    let utc = datetime.to_offset(time::UtcOffset::UTC);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        utc.year(),
+        utc.month() as u8,
+        utc.day(),
+        utc.hour(),
+        utc.minute(),
+        utc.second()
+    )
+}
+
+/// Internal helper to escape a TEXT value
+fn ical_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Internal helper to write a content line, folded so no line is longer than 75 octets
+fn write_line(f: &mut std::fmt::Formatter, line: &str) -> std::fmt::Result {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            f.write_str("\r\n ")?;
+            // The leading space counts towards the length
+            length = 1;
+        }
+        write!(f, "{c}")?;
+        length += c.len_utf8();
+    }
+    f.write_str("\r\n")
+}
+
+impl std::fmt::Display for Calendar {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write_line(f, "BEGIN:VCALENDAR")?;
+        write_line(f, "VERSION:2.0")?;
+        write_line(f, "PRODID:-//ptv//Rust PTV Timetable API//EN")?;
+        write_line(f, "CALSCALE:GREGORIAN")?;
+        if let Some(name) = &self.name {
+            write_line(f, &format!("X-WR-CALNAME:{}", ical_text(name)))?;
+        }
+        let timestamp = ical_datetime(self.timestamp);
+        for event in &self.events {
+            write_line(f, "BEGIN:VEVENT")?;
+            write_line(f, &format!("UID:{}", event.uid))?;
+            write_line(f, &format!("DTSTAMP:{timestamp}"))?;
+            write_line(f, &format!("DTSTART:{}", ical_datetime(event.start)))?;
+            if let Some(end) = event.end {
+                write_line(f, &format!("DTEND:{}", ical_datetime(end)))?;
+            }
+            write_line(f, &format!("SUMMARY:{}", ical_text(&event.summary)))?;
+            if let Some(description) = &event.description {
+                write_line(f, &format!("DESCRIPTION:{}", ical_text(description)))?;
+            }
+            if let Some(location) = &event.location {
+                write_line(f, &format!("LOCATION:{}", ical_text(location)))?;
+            }
+            if let Some(geo) = event.geo {
+                write_line(f, &format!("GEO:{};{}", geo.latitude, geo.longitude))?;
+            }
+            if let Some(url) = &event.url {
+                write_line(f, &format!("URL:{url}"))?;
+            }
+            write_line(f, "END:VEVENT")?;
+        }
+        write_line(f, "END:VCALENDAR")
+    }
+}
@@ -0,0 +1,194 @@
+use crate::*;
+use futures_util::{StreamExt, TryStreamExt};
+
+/// Version of the [NetworkIndex] file format, bumped whenever it changes incompatibly
+pub const INDEX_VERSION: u32 = 1;
+
+/// Maximum number of route requests made at once by [PTV::build_index]
+const INDEX_CONCURRENCY: usize = 4;
+
+/// A route in a [NetworkIndex]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedRoute {
+    /// The route
+    pub route: RouteDetails,
+    /// Directions of travel of the route
+    pub directions: Vec<DirectionDetails>,
+    /// Stops on the route
+    pub stops: Vec<StopId>,
+}
+
+/// A stop in a [NetworkIndex]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedStop {
+    /// The stop
+    pub stop: StopDetails,
+    /// Routes travelling through the stop
+    pub routes: Vec<RouteId>,
+}
+
+/// Snapshot of the static network (route types, routes, directions and stops) which can be queried offline
+///
+/// Build one with [PTV::build_index], then [NetworkIndex::write] it somewhere and [NetworkIndex::read] it back later.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct NetworkIndex {
+    /// File format version, see [INDEX_VERSION]
+    pub version: u32,
+    /// When the snapshot was taken
+    #[serde(with = "datetime")]
+    pub created_utc: OffsetDateTime,
+    /// Transport modes
+    pub route_types: Vec<RouteTypeDetails>,
+    /// Routes, ordered by route type then identifier
+    pub routes: Vec<IndexedRoute>,
+    /// Stops, ordered by route type then identifier
+    pub stops: Vec<IndexedStop>,
+}
+
+impl NetworkIndex {
+    /// Read an index previously written by [NetworkIndex::write]
+    pub fn read(mut reader: impl std::io::Read) -> Result<Self> {
+        let mut data = String::new();
+        reader
+            .read_to_string(&mut data)
+            .map_err(|e| Error::Other(format!("Failed to read index: {e}")))?;
+        let version: serde_json::Value = parse_json(&data)?;
+        match version.get("version").and_then(|v| v.as_u64()) {
+            Some(version) if version == INDEX_VERSION as u64 => {}
+            Some(version) => {
+                return Err(Error::Other(format!(
+                    "Unsupported index version {version}, expected {INDEX_VERSION}"
+                )))
+            }
+            None => return Err(Error::Other("Index is missing a version".to_string())),
+        }
+        let mut index: Self = parse_json(&data)?;
+        index.sort();
+        Ok(index)
+    }
+    /// Write the index as JSON
+    pub fn write(&self, writer: impl std::io::Write) -> Result<()> {
+        serde_json::to_writer(writer, self)
+            .map_err(|e| Error::Other(format!("Failed to write index: {e}")))
+    }
+    /// Internal helper to keep routes and stops ordered for lookups
+    fn sort(&mut self) {
+        self.routes.sort_by_key(|r| r.route.route());
+        self.stops.sort_by_key(|s| s.stop.stop());
+    }
+
+    /// Look up a stop by identifier
+    pub fn stop(&self, stop: &Stop) -> Option<&IndexedStop> {
+        let index = self
+            .stops
+            .binary_search_by(|s| s.stop.stop().cmp(stop))
+            .ok()?;
+        Some(&self.stops[index])
+    }
+    /// Look up a route by identifier
+    pub fn route(&self, route: &Route) -> Option<&IndexedRoute> {
+        let index = self
+            .routes
+            .binary_search_by(|r| r.route.route().cmp(route))
+            .ok()?;
+        Some(&self.routes[index])
+    }
+    /// Find a stop by name, the same way as [PTV::resolve_stop]
+    pub fn search_stops(&self, text: &str, params: &ResolveParams) -> StopResolution {
+        if let Ok(stop) = text.parse() {
+            return StopResolution::Literal(stop);
+        }
+        rank_stops(text, self.stops.iter().map(|s| s.stop.clone()), params)
+    }
+    /// Find routes by number (exactly) or name (partially), ignoring case
+    pub fn search_routes(&self, text: &str) -> Vec<&IndexedRoute> {
+        let text = text.trim().to_lowercase();
+        self.routes
+            .iter()
+            .filter(|r| {
+                r.route.route_number.to_lowercase() == text
+                    || r.route.route_name.to_lowercase().contains(&text)
+            })
+            .collect()
+    }
+    /// Stops within `max_distance` metres of a location, nearest first, with their distance
+    pub fn nearest_stops(
+        &self,
+        location: LatLon,
+        max_distance: f64,
+        max_results: usize,
+    ) -> Vec<(&IndexedStop, f64)> {
+        let mut nearest: Vec<_> = self
+            .stops
+            .iter()
+            .map(|s| (s, location.distance(&s.stop.location())))
+            .filter(|(_, distance)| *distance <= max_distance)
+            .collect();
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+        nearest.truncate(max_results);
+        nearest
+    }
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// Crawl route types, routes, and the stops and directions of each route into a [NetworkIndex]
+    ///
+    /// This makes two requests per route, so takes a while for the whole network.
+    pub async fn build_index(&self) -> Result<NetworkIndex> {
+        let route_types = self.route_types().await?.route_types;
+        let params = RoutesParams {
+            route_types: Some(route_types.iter().map(|rt| rt.route_type).collect()),
+            ..Default::default()
+        };
+        let routes = self.routes(params).await?.routes;
+
+        let routes: Vec<_> = futures_util::stream::iter(routes)
+            .map(|route| async move {
+                let directions = self.directions_for_route(&route.route_id).await?;
+                let stops = self
+                    .stops_on_route(&route.route(), Default::default())
+                    .await?;
+                Ok::<_, Error>((route, directions.directions, stops.stops))
+            })
+            .buffered(INDEX_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut stops: BTreeMap<Stop, IndexedStop> = BTreeMap::new();
+        let mut indexed_routes = Vec::new();
+        for (route, mut directions, route_stops) in routes {
+            directions.retain(|d| d.route_type == route.route_type);
+            let mut stop_ids = Vec::new();
+            for mut stop in route_stops {
+                stop_ids.push(stop.stop_id.clone());
+                // Details specific to this route (or search) aren't useful in the index
+                stop.stop_sequence = 0;
+                stop.stop_distance = 0.0;
+                stop.routes = None;
+                stop.disruption_ids = None;
+                let indexed = stops.entry(stop.stop()).or_insert_with(|| IndexedStop {
+                    stop,
+                    routes: Vec::new(),
+                });
+                if !indexed.routes.contains(&route.route_id) {
+                    indexed.routes.push(route.route_id.clone());
+                }
+            }
+            indexed_routes.push(IndexedRoute {
+                route,
+                directions,
+                stops: stop_ids,
+            });
+        }
+
+        let mut index = NetworkIndex {
+            version: INDEX_VERSION,
+            created_utc: OffsetDateTime::now_utc(),
+            route_types,
+            routes: indexed_routes,
+            stops: stops.into_values().collect(),
+        };
+        index.sort();
+        Ok(index)
+    }
+}
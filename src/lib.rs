@@ -1,21 +1,100 @@
 #![doc = include_str!("../README.md")]
 
+// Implement `UnknownFields` for a response type, recursing into the listed fields
+macro_rules! unknown_fields {
+    ($type:ty $(, $field:ident)*) => {
+        impl UnknownFields for $type {
+            fn collect_unknown_fields(&self, path: &str, found: &mut Vec<String>) {
+                found.extend(self.extra.keys().map(|name| join_path(path, name)));
+                $(self.$field.collect_unknown_fields(&join_path(path, stringify!($field)), found);)*
+            }
+        }
+    };
+}
+
+// Implement `ApiResponse` for a top level response type
+macro_rules! api_response {
+    ($type:ty) => {
+        impl ApiResponse for $type {
+            fn status(&self) -> &Status {
+                &self.status
+            }
+        }
+    };
+}
+
 #[cfg(feature = "reqwest")]
 mod reqwest;
 
 #[cfg(test)]
 mod test;
 
+#[cfg(feature = "watch")]
+mod watch;
+
+#[cfg(feature = "gtfs-realtime")]
+pub mod gtfs_realtime;
+
+#[cfg(feature = "proxy")]
+mod proxy;
+
+#[cfg(feature = "mock-server")]
+mod mock_server;
+
 mod api {
     pub mod departures;
+    pub mod directions;
+    pub mod disruptions;
+    pub mod patterns;
+    pub mod route_types;
+    pub mod routes;
+    pub mod runs;
     pub mod search;
+    pub mod stops;
 }
+mod cassette;
+mod diff;
+mod disruption_watcher;
+mod geo;
+mod gtfs;
+mod ical;
+mod index;
+mod metrics;
+mod nearby;
+mod planner;
+mod resolve;
+mod signature;
 mod types;
 
 pub use api::departures::*;
+pub use api::directions::*;
+pub use api::disruptions::*;
+pub use api::patterns::*;
+pub use api::route_types::*;
+pub use api::routes::*;
+pub use api::runs::*;
 pub use api::search::*;
+pub use api::stops::*;
 
+pub use cassette::*;
+pub use diff::*;
+pub use disruption_watcher::*;
+pub use geo::*;
+pub use gtfs::*;
+pub use ical::*;
+pub use index::*;
+pub use metrics::*;
+#[cfg(feature = "mock-server")]
+pub use mock_server::*;
+pub use nearby::*;
+pub use planner::*;
+#[cfg(feature = "proxy")]
+pub use proxy::*;
+pub use resolve::*;
+pub use signature::*;
 pub use types::*;
+#[cfg(feature = "watch")]
+pub use watch::*;
 
 use hmac::{Hmac, Mac};
 use http::StatusCode;
@@ -45,6 +124,8 @@ pub enum Error {
     Other(String),
     /// Parse error
     JsonParseError(String),
+    /// The response contained fields which aren't modelled by this crate (only in strict mode)
+    UnknownFields(Vec<String>),
     /// An error from the remote HTTP server
     HTTP(StatusCode, String),
     /// An error returned by the remote API endpoint
@@ -72,6 +153,67 @@ impl std::fmt::Display for Error {
     }
 }
 
+/// Parse a JSON API response, reporting the path to the field when parsing fails
+pub fn parse_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    let deserializer = &mut serde_json::Deserializer::from_str(json);
+    serde_path_to_error::deserialize(deserializer)
+        .map_err(|error| Error::JsonParseError(error.to_string()))
+}
+
+/// Types which keep hold of fields that weren't recognised during deserialization
+///
+/// Each response struct stores unrecognised fields in its `extra` map, this walks the
+/// whole response to find them, which is useful for detecting API schema changes.
+pub trait UnknownFields {
+    /// Append the path of every unrecognised field to `found`
+    fn collect_unknown_fields(&self, path: &str, found: &mut Vec<String>);
+
+    /// Paths of every unrecognised field (i.e. `departures[0].geopath`)
+    fn unknown_fields(&self) -> Vec<String> {
+        let mut found = Vec::new();
+        self.collect_unknown_fields("", &mut found);
+        found
+    }
+}
+impl<T: UnknownFields> UnknownFields for Option<T> {
+    fn collect_unknown_fields(&self, path: &str, found: &mut Vec<String>) {
+        if let Some(value) = self {
+            value.collect_unknown_fields(path, found);
+        }
+    }
+}
+impl<T: UnknownFields> UnknownFields for Vec<T> {
+    fn collect_unknown_fields(&self, path: &str, found: &mut Vec<String>) {
+        for (index, value) in self.iter().enumerate() {
+            value.collect_unknown_fields(&format!("{path}[{index}]"), found);
+        }
+    }
+}
+impl<K: std::fmt::Display, T: UnknownFields> UnknownFields for BTreeMap<K, T> {
+    fn collect_unknown_fields(&self, path: &str, found: &mut Vec<String>) {
+        for (key, value) in self {
+            value.collect_unknown_fields(&join_path(path, key), found);
+        }
+    }
+}
+
+/// Internal helper to build the path of a field for [UnknownFields]
+fn join_path(path: &str, name: impl std::fmt::Display) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{path}.{name}")
+    }
+}
+
+/// Internal helper to find the value following a segment of a URL path (i.e. `stop` in `/stop/1071`)
+#[cfg(feature = "tracing")]
+fn path_parameter<'a>(url: &'a Url, name: &str) -> Option<&'a str> {
+    let mut segments = url.path_segments()?;
+    segments.find(|segment| *segment == name)?;
+    segments.next()
+}
+
 /// A trait for abstracting the HTTP client library easily.
 ///
 /// See the `reqwest` module for the `reqwest` implementation of this trait
@@ -84,41 +226,194 @@ pub trait PTVHttpClient {
 pub struct PTV<HttpClient: PTVHttpClient> {
     http_client: HttpClient,
     devid: String,
-    key: String,
+    signer: Box<dyn Signer>,
     base: Url,
+    strict: bool,
+    observer: Option<Box<dyn MetricsObserver>>,
+}
+
+impl<Client: PTVHttpClient> std::fmt::Debug for PTV<Client> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // The signer is left out, it holds the credentials
+        f.debug_struct("PTV")
+            .field("devid", &self.devid)
+            .field("base", &self.base.as_str())
+            .field("strict", &self.strict)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<Client: PTVHttpClient> PTV<Client> {
+    /// Creates a new PTV client using any HTTP client (i.e. a [ReplayClient] in tests)
+    ///
+    /// * `devid`: The Developer ID provided by PTV (i.e. `"0"`)
+    /// * `key`: The Developer API key provided by PTV (i.e. `"00000000-0000-0000-0000-000000000000"`)
+    pub fn with_client(http_client: Client, devid: impl ToString, key: impl ToString) -> Self {
+        Self {
+            http_client,
+            devid: devid.to_string(),
+            signer: Box::new(HmacSigner::new(key)),
+            base: Url::parse(BASE_URL).expect("Failed to create base URL"),
+            strict: false,
+            observer: None,
+        }
+    }
+    /// Enable or disable strict mode
+    ///
+    /// In strict mode any response containing fields which aren't modelled by this crate
+    /// fails with [Error::UnknownFields], naming the fields. Otherwise (the default)
+    /// they're kept in the `extra` map of the struct they were found in.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+    /// Use a different signer for requests (the default is [HmacSigner] with the developer key)
+    pub fn with_signer(mut self, signer: impl Signer + 'static) -> Self {
+        self.signer = Box::new(signer);
+        self
+    }
+    /// Observe every request made, i.e. to collect metrics with [PrometheusMetrics]
+    pub fn with_observer(mut self, observer: impl MetricsObserver + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+    /// Use a different base URL for requests (i.e. a local test server or proxy)
+    pub fn with_base_url(mut self, base: Url) -> Self {
+        self.base = base;
+        self
+    }
+    /// Internal helper to fetch and parse an endpoint, checking for unknown fields in strict mode
+    ///
+    /// `operation` is the Swagger operation name (i.e. `Departures_GetForStop`), which is
+    /// passed to the observer, and recorded in a span with the `tracing` feature. The span also
+    /// records the HTTP `status`, the `bytes` in the response, and `parse_ms` taken to parse it.
+    async fn get<T>(&self, operation: &'static str, url: Url) -> Result<T>
+    where
+        T: DeserializeOwned + UnknownFields + ApiResponse + Send + 'static,
+    {
+        let start = std::time::Instant::now();
+        #[cfg(feature = "tracing")]
+        let span = {
+            use tracing::field::Empty;
+            let span = tracing::info_span!(
+                "ptv",
+                operation,
+                url = %redacted_url(&url),
+                route_type = Empty,
+                stop_id = Empty,
+                status = Empty,
+                bytes = Empty,
+                parse_ms = Empty,
+            );
+            if let Some(route_type) = path_parameter(&url, "route_type") {
+                span.record("route_type", route_type);
+            }
+            if let Some(stop_id) = path_parameter(&url, "stop") {
+                span.record("stop_id", stop_id);
+            }
+            span
+        };
+        #[cfg(feature = "tracing")]
+        let result = tracing::Instrument::instrument(self.fetch::<T>(url), span.clone()).await;
+        #[cfg(not(feature = "tracing"))]
+        let result = self.fetch::<T>(url).await;
+        let latency = start.elapsed();
+
+        #[cfg(feature = "tracing")]
+        span.in_scope(|| {
+            let latency_ms = latency.as_secs_f64() * 1000.0;
+            match &result {
+                Ok(_) => tracing::debug!(latency_ms, "Request succeeded"),
+                Err(error) => {
+                    let status = match error {
+                        Error::API(status, _) | Error::HTTP(status, _) => Some(status.as_u16()),
+                        _ => None,
+                    };
+                    tracing::warn!(latency_ms, status, %error, "Request failed")
+                }
+            }
+        });
+        if let Some(observer) = &self.observer {
+            let health = match &result {
+                Ok(response) => Some(response.status().health),
+                Err(Error::API(_, error)) => Some(error.status.health),
+                Err(_) => None,
+            };
+            observer.observe(&RequestMetrics {
+                operation,
+                outcome: RequestOutcome::of(&result),
+                latency,
+                health,
+            });
+        }
+        result
+    }
+    /// Internal helper for [PTV::get], recording the response in the current span
+    ///
+    /// The response is parsed here rather than by the HTTP client, so its size and the time
+    /// taken to parse it are known whichever client is used.
+    async fn fetch<T>(&self, url: Url) -> Result<T>
+    where
+        T: DeserializeOwned + UnknownFields + Send + 'static,
+    {
+        let raw = self
+            .http_client
+            .api_get::<Box<serde_json::value::RawValue>>(url)
+            .await;
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            match &raw {
+                Ok(raw) => {
+                    span.record("status", StatusCode::OK.as_u16());
+                    span.record("bytes", raw.get().len());
+                }
+                Err(Error::API(status, _)) => {
+                    span.record("status", status.as_u16());
+                }
+                Err(Error::HTTP(status, body)) => {
+                    span.record("status", status.as_u16());
+                    span.record("bytes", body.len());
+                }
+                Err(_) => {}
+            }
+        }
+        let raw = raw?;
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+        let response = parse_json::<T>(raw.get());
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("parse_ms", start.elapsed().as_secs_f64() * 1000.0);
+        let response = response?;
+        if self.strict {
+            let unknown = response.unknown_fields();
+            if !unknown.is_empty() {
+                return Err(Error::UnknownFields(unknown));
+            }
+        }
+        Ok(response)
+    }
     /// Internal helper to build a complete endpoint URL given an API path and parameters
-    fn build_url(&self, path: &str, params: impl Serialize) -> Url {
+    async fn build_url(&self, path: &str, params: impl Serialize) -> Result<Url> {
         let query = serde_html_form::to_string(&params).expect("Failed to serialize URL params");
         let mut url = self.base.join(path).expect("Failed to build URL");
         url.set_query(Some(&query));
-        self.sign_url(url)
+        self.sign_url(url).await
     }
     /// Internal helper to sign an endpoint URL using the supplied developer id and key
-    fn sign_url(&self, mut url: Url) -> Url {
+    async fn sign_url(&self, mut url: Url) -> Result<Url> {
         // Add developer id to the request
         url.query_pairs_mut().append_pair("devid", &self.devid);
 
         // Sign the path and query part of the URL, with developer id
-        let signature = HmacSha1::new_from_slice(self.key.as_bytes())
-            .expect("Failed to initialize HMAC")
-            .chain_update(url.path())
-            .chain_update(b"?")
-            .chain_update(url.query().unwrap_or_default())
-            .finalize();
-
-        // Convert it to a hex string
-        let hex: String = signature
-            .into_bytes()
-            .iter()
-            .map(|b| format!("{b:02X}"))
-            .collect();
+        let signature = self
+            .signer
+            .sign(url.path(), url.query().unwrap_or_default())
+            .await?;
 
         // Append it to the URL
-        url.query_pairs_mut().append_pair("signature", &hex);
+        url.query_pairs_mut().append_pair("signature", &signature);
 
-        url
+        Ok(url)
     }
 }
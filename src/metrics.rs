@@ -0,0 +1,318 @@
+use crate::*;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Responses from the API, which all include its status
+pub trait ApiResponse {
+    /// API status / Metadata
+    fn status(&self) -> &Status;
+}
+
+/// How a request turned out, see [RequestMetrics]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestOutcome {
+    /// The response was parsed successfully
+    Success,
+    /// The API returned an error, see [Error::API]
+    ApiError(StatusCode),
+    /// The HTTP server returned an error, see [Error::HTTP]
+    HttpError(StatusCode),
+    /// The response couldn't be parsed, see [Error::JsonParseError]
+    ParseError,
+    /// The response had fields which aren't modelled (only in strict mode), see [Error::UnknownFields]
+    UnknownFields,
+    /// The request failed for some other reason (i.e. the connection failed)
+    Other,
+}
+impl RequestOutcome {
+    /// Outcome of a request from its result
+    pub fn of<T>(result: &Result<T>) -> Self {
+        match result {
+            Ok(_) => Self::Success,
+            Err(Error::API(status, _)) => Self::ApiError(*status),
+            Err(Error::HTTP(status, _)) => Self::HttpError(*status),
+            Err(Error::JsonParseError(_)) => Self::ParseError,
+            Err(Error::UnknownFields(_)) => Self::UnknownFields,
+            Err(Error::Other(_)) => Self::Other,
+        }
+    }
+    /// Short name of the outcome, i.e. for metric labels
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::ApiError(_) => "api_error",
+            Self::HttpError(_) => "http_error",
+            Self::ParseError => "parse_error",
+            Self::UnknownFields => "unknown_fields",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Details of a request made to the API, passed to [MetricsObserver::observe]
+#[derive(Clone, Debug)]
+pub struct RequestMetrics {
+    /// Swagger operation name (i.e. `Departures_GetForStop`)
+    pub operation: &'static str,
+    /// How the request turned out
+    pub outcome: RequestOutcome,
+    /// Time taken to make the request and parse the response
+    pub latency: Duration,
+    /// Health of the API, from the status of the response (if there was one)
+    pub health: Option<HealthStatus>,
+}
+
+/// Observes every request made to the API, see [PTV::with_observer]
+pub trait MetricsObserver: Send + Sync {
+    /// Called after each request completes
+    fn observe(&self, request: &RequestMetrics);
+}
+impl<T: MetricsObserver> MetricsObserver for std::sync::Arc<T> {
+    fn observe(&self, request: &RequestMetrics) {
+        T::observe(self, request)
+    }
+}
+
+impl DeparturesResponse {
+    /// Mean delay of departures with a real-time estimate, for each route
+    pub fn route_delays(&self) -> BTreeMap<RouteId, time::Duration> {
+        let mut delays: BTreeMap<RouteId, Vec<time::Duration>> = BTreeMap::new();
+        for departure in self.resolved() {
+            if let Some(delay) = departure.delay() {
+                delays
+                    .entry(departure.route_id.clone())
+                    .or_default()
+                    .push(delay);
+            }
+        }
+        delays
+            .into_iter()
+            .map(|(route_id, delays)| {
+                let total: time::Duration = delays.iter().sum();
+                (route_id, total / delays.len() as u32)
+            })
+            .collect()
+    }
+    /// Number of cancelled runs (requires `include_cancelled` and `Run` expansion)
+    pub fn cancelled_runs(&self) -> usize {
+        self.runs
+            .values()
+            .filter(|run| run.status.eq_ignore_ascii_case("cancelled"))
+            .count()
+    }
+}
+
+impl Disruptions {
+    /// Number of current (i.e. not planned) disruptions, for each disruption type
+    pub fn active_by_type(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for disruption in self.iter() {
+            if disruption.disruption_status.eq_ignore_ascii_case("current") {
+                *counts
+                    .entry(disruption.disruption_type.clone())
+                    .or_default() += 1;
+            }
+        }
+        counts
+    }
+}
+
+/// Upper bounds of the request duration histogram buckets, in seconds
+const DURATION_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Internal state of a request duration histogram
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// Internal state of [PrometheusMetrics]
+#[derive(Debug, Default)]
+struct PrometheusState {
+    requests: BTreeMap<(&'static str, &'static str), u64>,
+    durations: BTreeMap<&'static str, Histogram>,
+    health: Option<HealthStatus>,
+    route_delays: BTreeMap<(Stop, RouteId), (String, f64)>,
+    cancelled_runs: BTreeMap<(RouteType, StopId), usize>,
+    active_disruptions: BTreeMap<String, usize>,
+}
+
+/// Collects metrics, and exports them in the Prometheus text format
+///
+/// Request metrics are collected by passing it (in an `Arc`) to [PTV::with_observer]. Domain
+/// metrics are collected by passing responses to [PrometheusMetrics::record_departures] and
+/// [PrometheusMetrics::record_disruptions]. `ptv_api_online` is `0` when the API reports
+/// itself as [HealthStatus::OFFLINE], which is useful for alerting.
+#[derive(Debug, Default)]
+pub struct PrometheusMetrics {
+    state: Mutex<PrometheusState>,
+}
+
+impl PrometheusMetrics {
+    /// No metrics collected yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Internal helper to access the state
+    fn state(&self) -> std::sync::MutexGuard<'_, PrometheusState> {
+        self.state.lock().expect("Metrics poisoned")
+    }
+
+    /// Replace the delay of each route, and the number of cancelled runs at the stop
+    ///
+    /// Routes of the stop without real-time estimates in `response` no longer have a delay.
+    pub fn record_departures(&self, stop: &Stop, response: &DeparturesResponse) {
+        let delays = response.route_delays();
+        let cancelled = response.cancelled_runs();
+        let mut state = self.state();
+        state
+            .route_delays
+            .retain(|(existing, _), _| existing != stop);
+        for (route_id, delay) in delays {
+            let name = response
+                .routes
+                .get(&route_id)
+                .map(|route| route.route_name.clone())
+                .unwrap_or_default();
+            let key = (stop.clone(), route_id);
+            state
+                .route_delays
+                .insert(key, (name, delay.as_seconds_f64()));
+        }
+        state
+            .cancelled_runs
+            .insert((stop.route_type, stop.id.clone()), cancelled);
+    }
+
+    /// Replace the number of active disruptions of each type
+    pub fn record_disruptions(&self, disruptions: &Disruptions) {
+        self.state().active_disruptions = disruptions.active_by_type();
+    }
+
+    /// All metrics, in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let state = self.state();
+        let mut out = String::new();
+
+        let name = "ptv_requests_total";
+        metric_header(&mut out, name, "counter", "Requests made to the PTV API");
+        for ((operation, outcome), count) in &state.requests {
+            let labels = labels(&[("operation", operation), ("outcome", outcome)]);
+            let _ = writeln!(out, "{name}{labels} {count}");
+        }
+
+        let name = "ptv_request_duration_seconds";
+        metric_header(
+            &mut out,
+            name,
+            "histogram",
+            "Latency of requests to the PTV API",
+        );
+        for (operation, histogram) in &state.durations {
+            let mut cumulative = 0;
+            for (bound, count) in DURATION_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let labels = labels(&[("operation", operation), ("le", &bound.to_string())]);
+                let _ = writeln!(out, "{name}_bucket{labels} {cumulative}");
+            }
+            let labels_inf = labels(&[("operation", operation), ("le", "+Inf")]);
+            let labels = labels(&[("operation", operation)]);
+            let _ = writeln!(out, "{name}_bucket{labels_inf} {}", histogram.count);
+            let _ = writeln!(out, "{name}_sum{labels} {}", histogram.sum);
+            let _ = writeln!(out, "{name}_count{labels} {}", histogram.count);
+        }
+
+        if let Some(health) = state.health {
+            let name = "ptv_api_online";
+            metric_header(
+                &mut out,
+                name,
+                "gauge",
+                "Whether the PTV API reports itself online",
+            );
+            let online = u8::from(health == HealthStatus::ONLINE);
+            let _ = writeln!(out, "{name} {online}");
+        }
+
+        let name = "ptv_route_delay_seconds";
+        metric_header(
+            &mut out,
+            name,
+            "gauge",
+            "Mean current delay of departures on a route from a stop",
+        );
+        for ((stop, route_id), (route_name, delay)) in &state.route_delays {
+            let labels = labels(&[
+                ("route_type", &stop.route_type.to_string()),
+                ("stop_id", &stop.id.to_string()),
+                ("route_id", &route_id.to_string()),
+                ("route_name", route_name),
+            ]);
+            let _ = writeln!(out, "{name}{labels} {delay}");
+        }
+
+        let name = "ptv_cancelled_runs";
+        metric_header(&mut out, name, "gauge", "Cancelled runs departing a stop");
+        for ((route_type, stop_id), count) in &state.cancelled_runs {
+            let labels = labels(&[
+                ("route_type", &route_type.to_string()),
+                ("stop_id", &stop_id.to_string()),
+            ]);
+            let _ = writeln!(out, "{name}{labels} {count}");
+        }
+
+        let name = "ptv_active_disruptions";
+        metric_header(&mut out, name, "gauge", "Current disruptions by type");
+        for (disruption_type, count) in &state.active_disruptions {
+            let labels = labels(&[("disruption_type", disruption_type)]);
+            let _ = writeln!(out, "{name}{labels} {count}");
+        }
+
+        out
+    }
+}
+
+impl MetricsObserver for PrometheusMetrics {
+    fn observe(&self, request: &RequestMetrics) {
+        let mut state = self.state();
+        let key = (request.operation, request.outcome.label());
+        *state.requests.entry(key).or_default() += 1;
+
+        let seconds = request.latency.as_secs_f64();
+        let histogram = state.durations.entry(request.operation).or_default();
+        if let Some(bucket) = DURATION_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            histogram.buckets[bucket] += 1;
+        }
+        histogram.sum += seconds;
+        histogram.count += 1;
+
+        if let Some(health) = request.health {
+            state.health = Some(health);
+        }
+    }
+}
+
+/// Internal helper to write the `HELP` and `TYPE` lines of a metric
+fn metric_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Internal helper to format metric labels (i.e. `{operation="Search_Search"}`)
+fn labels(labels: &[(&str, &str)]) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
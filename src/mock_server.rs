@@ -0,0 +1,264 @@
+use crate::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Departures which are served relative to the time of each request, see [MockServer::with_departures]
+#[derive(Clone, Debug)]
+struct RelativeDepartures {
+    response: DeparturesResponse,
+    recorded_at: OffsetDateTime,
+}
+
+/// Internal state of [MockServer] which can be changed while it's serving
+#[derive(Debug)]
+struct MockState {
+    latency: Duration,
+    health: HealthStatus,
+    faults: Vec<(String, StatusCode)>,
+}
+
+/// Response from [MockServer::handle]
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    /// HTTP status
+    pub status: StatusCode,
+    /// JSON body
+    pub body: String,
+}
+
+/// Local stand-in for the PTV API, for integration tests which can't use the network
+///
+/// Serves `GET /v3/...` from fixture JSON, checking the `devid` and `signature` of each request
+/// exactly as the real API does (failing with `403 Forbidden`). Requests without a fixture fail
+/// with `404 Not Found`. Latency, errors and the reported health can be changed while serving,
+/// see [MockServer::set_latency], [MockServer::set_fault] and [MockServer::set_health].
+///
+/// Use [MockServer::spawn] to serve it on a local port, and point [PTV::with_base_url] at it.
+#[derive(Debug)]
+pub struct MockServer {
+    devid: String,
+    signer: HmacSigner,
+    fixtures: HashMap<String, String>,
+    departures: HashMap<String, RelativeDepartures>,
+    state: Mutex<MockState>,
+}
+
+impl MockServer {
+    /// Accept requests signed with these credentials, see [PTV::new]
+    pub fn new(devid: impl ToString, key: impl ToString) -> Self {
+        Self {
+            devid: devid.to_string(),
+            signer: HmacSigner::new(key),
+            fixtures: HashMap::new(),
+            departures: HashMap::new(),
+            state: Mutex::new(MockState {
+                latency: Duration::ZERO,
+                health: HealthStatus::ONLINE,
+                faults: Vec::new(),
+            }),
+        }
+    }
+
+    /// Serve a JSON body for a path (i.e. `/v3/route_types`)
+    ///
+    /// The path can include a query (i.e. `/v3/search/Flinders?route_types=0`) to only match
+    /// requests with exactly those parameters, otherwise any parameters match.
+    pub fn with_fixture(mut self, path: &str, json: impl ToString) -> Self {
+        self.fixtures.insert(path.to_string(), json.to_string());
+        self
+    }
+
+    /// Serve every successful response recorded in a cassette, see [RecordingClient]
+    pub fn with_cassette(mut self, cassette: &Cassette) -> Self {
+        for interaction in &cassette.interactions {
+            if (200..300).contains(&interaction.status) {
+                let json = interaction.response.to_string();
+                self.fixtures.insert(interaction.request.clone(), json);
+            }
+        }
+        self
+    }
+
+    /// Serve departures from a stop, moved in time as though it's always `recorded_at`
+    ///
+    /// Each request shifts the scheduled and estimated departure times by the time since
+    /// `recorded_at`, so a departure 5 minutes after `recorded_at` is always 5 minutes away.
+    /// This is useful for testing logic which depends on the current time.
+    pub fn with_departures(
+        mut self,
+        stop: &Stop,
+        response: DeparturesResponse,
+        recorded_at: OffsetDateTime,
+    ) -> Self {
+        let path = format!(
+            "/v3/departures/route_type/{}/stop/{}",
+            stop.route_type.value(),
+            stop.id.value()
+        );
+        let departures = RelativeDepartures {
+            response,
+            recorded_at,
+        };
+        self.departures.insert(path, departures);
+        self
+    }
+
+    /// Internal helper to access the state
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("Mock server poisoned")
+    }
+
+    /// Delay every response (default = no delay)
+    pub fn set_latency(&self, latency: Duration) {
+        self.state().latency = latency;
+    }
+
+    /// Fail requests for paths starting with `prefix` with an error status (i.e. `429 Too Many
+    /// Requests`), or stop failing them with `None`
+    ///
+    /// Use `/v3/` to fail every request. Credentials are still checked first, as the real API does.
+    pub fn set_fault(&self, prefix: &str, status: Option<StatusCode>) {
+        let mut state = self.state();
+        state.faults.retain(|(existing, _)| existing != prefix);
+        if let Some(status) = status {
+            state.faults.push((prefix.to_string(), status));
+        }
+    }
+
+    /// Health reported in the status of every response (default = [HealthStatus::ONLINE])
+    pub fn set_health(&self, health: HealthStatus) {
+        self.state().health = health;
+    }
+
+    /// Respond to a signed request path and query (i.e. `/v3/route_types?devid=0&signature=...`)
+    pub async fn handle(&self, path_and_query: &str) -> MockResponse {
+        let latency = self.state().latency;
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        let response = self.respond(path_and_query);
+        let health = self.state().health;
+        match serde_json::from_str::<serde_json::Value>(&response.body) {
+            Ok(mut json) if json["status"].is_object() => {
+                json["status"]["health"] = serde_json::json!(health);
+                MockResponse {
+                    status: response.status,
+                    body: json.to_string(),
+                }
+            }
+            _ => response,
+        }
+    }
+
+    /// Internal helper to find the response to a request, before latency and health are applied
+    fn respond(&self, path_and_query: &str) -> MockResponse {
+        let Ok(url) = Url::parse("http://localhost/").and_then(|base| base.join(path_and_query))
+        else {
+            return Self::error(StatusCode::BAD_REQUEST, "Invalid request");
+        };
+        if let Err(error) = self.signer.verify(&url) {
+            return Self::error(StatusCode::FORBIDDEN, &error.to_string());
+        }
+        if !url
+            .query_pairs()
+            .any(|(name, devid)| name == "devid" && devid == self.devid)
+        {
+            return Self::error(StatusCode::FORBIDDEN, "Invalid devid");
+        }
+
+        let path = url.path();
+        let fault = self
+            .state()
+            .faults
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, status)| *status);
+        if let Some(status) = fault {
+            let message = status.canonical_reason().unwrap_or("Error");
+            return Self::error(status, message);
+        }
+
+        if let Some(departures) = self.departures.get(path) {
+            return Self::departures(departures);
+        }
+        let fixture = self
+            .fixtures
+            .get(&cassette_key(&url))
+            .or_else(|| self.fixtures.get(path));
+        match fixture {
+            Some(json) => MockResponse {
+                status: StatusCode::OK,
+                body: json.clone(),
+            },
+            None => Self::error(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+
+    /// Internal helper to shift departures to be relative to now
+    fn departures(departures: &RelativeDepartures) -> MockResponse {
+        let now = OffsetDateTime::now_utc()
+            .replace_nanosecond(0)
+            .expect("Failed to truncate time");
+        let shift = now - departures.recorded_at;
+        let mut response = departures.response.clone();
+        for departure in &mut response.departures {
+            departure.scheduled_departure_utc += shift;
+            departure.estimated_departure_utc =
+                departure.estimated_departure_utc.map(|t| t + shift);
+        }
+        match serde_json::to_string(&response) {
+            Ok(body) => MockResponse {
+                status: StatusCode::OK,
+                body,
+            },
+            Err(error) => Self::error(StatusCode::INTERNAL_SERVER_ERROR, &error.to_string()),
+        }
+    }
+
+    /// Internal helper to build an error in the same shape as the API's
+    fn error(status: StatusCode, message: &str) -> MockResponse {
+        let body = serde_json::json!({
+            "message": message,
+            "status": { "version": "3.0", "health": HealthStatus::ONLINE },
+        });
+        MockResponse {
+            status,
+            body: body.to_string(),
+        }
+    }
+
+    /// An `axum` router serving `GET /v3/...` from this server
+    pub fn router(self: &Arc<Self>) -> axum::Router {
+        use axum::extract::State;
+        use axum::http::{header, StatusCode, Uri};
+
+        async fn mock(
+            State(server): State<Arc<MockServer>>,
+            uri: Uri,
+        ) -> impl axum::response::IntoResponse {
+            let path_and_query = uri.path_and_query().map_or("/", |pq| pq.as_str());
+            let response = server.handle(path_and_query).await;
+            let status = StatusCode::from_u16(response.status.as_u16())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (
+                status,
+                [(header::CONTENT_TYPE, "application/json")],
+                response.body,
+            )
+        }
+
+        axum::Router::new()
+            .route("/v3/{*path}", axum::routing::get(mock))
+            .with_state(self.clone())
+    }
+
+    /// Serve on a free local port in the background, returning the base URL to use
+    pub async fn spawn(self: &Arc<Self>) -> std::io::Result<Url> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let router = self.router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Ok(Url::parse(&format!("http://{address}/")).expect("Failed to create base URL"))
+    }
+}
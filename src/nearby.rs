@@ -0,0 +1,86 @@
+use crate::*;
+
+/// A departure from a stop near a location, returned by [PTV::departures_near]
+#[derive(Clone, Debug)]
+pub struct NearbyDeparture {
+    /// The stop being departed from
+    pub stop: StopDetails,
+    /// Distance from the location to the stop (in metres).
+    /// This is a straight line, so the actual walk will be a bit further.
+    pub walking_distance: f64,
+    /// The departure
+    pub departure: DepartureDetails,
+    /// The route of the departing service (if expanded)
+    pub route: Option<RouteDetails>,
+    /// The trip/service run that's departing (if expanded)
+    pub run: Option<RunDetails>,
+}
+impl NearbyDeparture {
+    /// Best known departure time, the real-time estimate if there is one, otherwise the scheduled time
+    pub fn departure_time(&self) -> OffsetDateTime {
+        self.departure
+            .estimated_departure_utc
+            .unwrap_or(self.departure.scheduled_departure_utc)
+    }
+}
+
+/// Response from [PTV::departures_near]
+#[derive(Clone, Debug)]
+pub struct NearbyDepartures {
+    /// Departures from every stop nearby, ordered by best known departure time
+    pub departures: Vec<NearbyDeparture>,
+    /// Stops where departures couldn't be fetched, with the reason
+    pub errors: Vec<(Stop, Error)>,
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// View departures from all stops within `radius` metres of a location
+    ///
+    /// Stops are found with [PTV::stops_near], then departures are fetched for each of them.
+    /// `concurrency` is the maximum number of departures requests made at once (at least 1),
+    /// as for [PTV::departures_many]. Expand `Route` and `Run` in `params` to fill in
+    /// [NearbyDeparture::route] and [NearbyDeparture::run].
+    pub async fn departures_near(
+        &self,
+        location: LatLon,
+        radius: f64,
+        route_types: Option<Vec<RouteType>>,
+        params: DeparturesParams,
+        concurrency: usize,
+    ) -> Result<NearbyDepartures> {
+        let stops_params = StopsByDistanceParams {
+            route_types,
+            max_distance: Some(radius),
+            ..Default::default()
+        };
+        let stops = self.stops_near(location, stops_params).await?.stops;
+
+        // Responses are kept per stop, as stop identifiers are only unique within a route type
+        let requested: Vec<Stop> = stops.iter().map(StopDetails::stop).collect();
+        let results = self.departures_each(&requested, params, concurrency).await;
+
+        let mut departures = Vec::new();
+        let mut errors = Vec::new();
+        for (stop, (requested, result)) in stops.iter().zip(results) {
+            let response = match result {
+                Ok(response) => response,
+                Err(error) => {
+                    errors.push((requested.clone(), error));
+                    continue;
+                }
+            };
+            let walking_distance = location.distance(&stop.location());
+            for departure in response.resolved() {
+                departures.push(NearbyDeparture {
+                    stop: stop.clone(),
+                    walking_distance,
+                    departure: departure.details().clone(),
+                    route: departure.route().cloned(),
+                    run: departure.run().cloned(),
+                });
+            }
+        }
+        departures.sort_by_key(NearbyDeparture::departure_time);
+        Ok(NearbyDepartures { departures, errors })
+    }
+}
@@ -0,0 +1,318 @@
+use crate::*;
+use futures_util::StreamExt;
+
+/// Options for [PTV::plan_journey]
+#[derive(Clone, Debug)]
+pub struct JourneyParams {
+    /// Maximum number of departures per route considered at the origin and destination (default = 5)
+    pub max_results: i32,
+    /// Minimum time allowed to change services at a transfer stop (default = 3 minutes)
+    pub min_transfer: time::Duration,
+    /// Whether to look for journeys with one transfer (default = true)
+    pub allow_transfer: bool,
+    /// Maximum number of itineraries returned (default = 5)
+    pub max_itineraries: usize,
+    /// Maximum number of stopping pattern requests made at once (default = 4)
+    pub concurrency: usize,
+}
+impl Default for JourneyParams {
+    fn default() -> Self {
+        Self {
+            max_results: 5,
+            min_transfer: time::Duration::minutes(3),
+            allow_transfer: true,
+            max_itineraries: 5,
+            concurrency: 4,
+        }
+    }
+}
+
+/// A trip on a single service, part of an [Itinerary]
+#[derive(Clone, Debug)]
+pub struct Leg {
+    /// The trip/service run
+    pub run: Run,
+    /// Route identifier
+    pub route_id: RouteId,
+    /// Name of route (if known)
+    pub route_name: Option<String>,
+    /// Name of destination of run (if known)
+    pub destination_name: Option<String>,
+    /// Departure from the stop where the service is boarded
+    pub board: DepartureDetails,
+    /// Departure from the stop where the service is left.
+    /// The API only has departure times, so this is also used as the arrival time.
+    pub alight: DepartureDetails,
+}
+impl Leg {
+    /// Stop where the service is boarded
+    pub fn from(&self) -> Stop {
+        Stop {
+            route_type: self.run.route_type,
+            id: self.board.stop_id.clone(),
+        }
+    }
+    /// Stop where the service is left
+    pub fn to(&self) -> Stop {
+        Stop {
+            route_type: self.run.route_type,
+            id: self.alight.stop_id.clone(),
+        }
+    }
+    /// Best known departure time (real-time estimate if available)
+    pub fn departure_time(&self) -> OffsetDateTime {
+        best_time(&self.board)
+    }
+    /// Best known arrival time (real-time estimate if available)
+    pub fn arrival_time(&self) -> OffsetDateTime {
+        best_time(&self.alight)
+    }
+}
+
+/// A way to get from one stop to another, returned by [PTV::plan_journey]
+///
+/// There's always at least one leg.
+#[derive(Clone, Debug)]
+pub struct Itinerary {
+    legs: Vec<Leg>,
+}
+impl Itinerary {
+    /// Services taken, in order
+    pub fn legs(&self) -> &[Leg] {
+        &self.legs
+    }
+    /// Best known time the journey starts
+    pub fn departure_time(&self) -> OffsetDateTime {
+        self.legs[0].departure_time()
+    }
+    /// Best known time the journey ends
+    pub fn arrival_time(&self) -> OffsetDateTime {
+        self.legs[self.legs.len() - 1].arrival_time()
+    }
+    /// Time between departing and arriving
+    pub fn duration(&self) -> time::Duration {
+        self.arrival_time() - self.departure_time()
+    }
+    /// Number of changes between services
+    pub fn transfers(&self) -> usize {
+        self.legs.len() - 1
+    }
+}
+
+fn best_time(departure: &DepartureDetails) -> OffsetDateTime {
+    departure
+        .estimated_departure_utc
+        .unwrap_or(departure.scheduled_departure_utc)
+}
+
+/// Stopping pattern of a run
+struct Pattern {
+    run: Run,
+    response: PatternResponse,
+}
+impl Pattern {
+    fn new(run: Run, mut response: PatternResponse) -> Self {
+        response.departures.sort_by_key(|d| d.departure_sequence);
+        Self { run, response }
+    }
+    fn stops(&self) -> &[DepartureDetails] {
+        &self.response.departures
+    }
+    /// Index of the first time the run departs the stop, at or after a time
+    fn find(&self, stop_id: &StopId, after: OffsetDateTime) -> Option<usize> {
+        self.stops()
+            .iter()
+            .position(|d| &d.stop_id == stop_id && best_time(d) >= after)
+    }
+    fn leg(&self, board: usize, alight: usize) -> Leg {
+        let stops = self.stops();
+        let route_id = stops[board].route_id.clone();
+        Leg {
+            run: self.run.clone(),
+            route_name: self
+                .response
+                .routes
+                .get(&route_id)
+                .map(|route| route.route_name.clone()),
+            destination_name: self
+                .response
+                .runs
+                .get(&self.run.id)
+                .map(|run| run.destination_name.clone()),
+            route_id,
+            board: stops[board].clone(),
+            alight: stops[alight].clone(),
+        }
+    }
+}
+
+/// Internal helper to build itineraries from the stopping patterns of runs from the origin and to the destination
+fn itineraries(
+    origin: &Stop,
+    destination: &Stop,
+    departure_time: OffsetDateTime,
+    from_origin: &[Pattern],
+    to_destination: &[Pattern],
+    params: &JourneyParams,
+) -> Vec<Itinerary> {
+    let mut found = Vec::new();
+    for first in from_origin {
+        let Some(board) = first.find(&origin.id, departure_time) else {
+            continue;
+        };
+        let after_board = best_time(&first.stops()[board]);
+        if let Some(alight) = first.find(&destination.id, after_board) {
+            found.push(Itinerary {
+                legs: vec![first.leg(board, alight)],
+            });
+        }
+        if !params.allow_transfer {
+            continue;
+        }
+        for second in to_destination {
+            if second.run == first.run {
+                continue;
+            }
+            // Change at the first stop where the connection can be made
+            let transfer = (board + 1..first.stops().len()).find_map(|change| {
+                let stop_id = &first.stops()[change].stop_id;
+                if stop_id == &origin.id || stop_id == &destination.id {
+                    return None;
+                }
+                let earliest = best_time(&first.stops()[change]) + params.min_transfer;
+                let second_board = second.find(stop_id, earliest)?;
+                let after = best_time(&second.stops()[second_board]);
+                let second_alight = second.find(&destination.id, after)?;
+                Some((change, second_board, second_alight))
+            });
+            if let Some((change, second_board, second_alight)) = transfer {
+                found.push(Itinerary {
+                    legs: vec![
+                        first.leg(board, change),
+                        second.leg(second_board, second_alight),
+                    ],
+                });
+            }
+        }
+    }
+
+    // Drop itineraries where another leaves no earlier and arrives no later with no more transfers
+    let dominated = |itinerary: &Itinerary| {
+        found.iter().any(|other| {
+            let no_worse = other.departure_time() >= itinerary.departure_time()
+                && other.arrival_time() <= itinerary.arrival_time()
+                && other.transfers() <= itinerary.transfers();
+            let better = other.departure_time() > itinerary.departure_time()
+                || other.arrival_time() < itinerary.arrival_time()
+                || other.transfers() < itinerary.transfers();
+            no_worse && better
+        })
+    };
+    let mut ranked: Vec<_> = found
+        .iter()
+        .filter(|itinerary| !dominated(itinerary))
+        .cloned()
+        .collect();
+    ranked.sort_by_key(|itinerary| {
+        (
+            itinerary.arrival_time(),
+            itinerary.transfers(),
+            std::cmp::Reverse(itinerary.departure_time()),
+        )
+    });
+    ranked.dedup_by(|a, b| {
+        let runs = |itinerary: &Itinerary| -> Vec<Run> {
+            itinerary.legs.iter().map(|leg| leg.run.clone()).collect()
+        };
+        runs(a) == runs(b)
+    });
+    ranked.truncate(params.max_itineraries);
+    ranked
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// Find ways to get from one stop to another, leaving at or after `departure_time`
+    ///
+    /// This only uses departures and stopping patterns, so it finds direct services and services
+    /// with one transfer at a stop they share, ranked by arrival time. Real-time estimates are used
+    /// where available. The origin and destination need to be the same mode for a transfer to be found.
+    /// Runs whose stopping pattern can't be fetched are left out, rather than failing the plan.
+    pub async fn plan_journey(
+        &self,
+        origin: &Stop,
+        destination: &Stop,
+        departure_time: OffsetDateTime,
+        params: JourneyParams,
+    ) -> Result<Vec<Itinerary>> {
+        let date_utc = Some(datetime::to_string(departure_time));
+        let departures_params = DeparturesParams {
+            date_utc: date_utc.clone(),
+            max_results: Some(params.max_results),
+            expand: Some(vec![ResponseExpand::Run, ResponseExpand::Route]),
+            ..Default::default()
+        };
+        let pattern_params = PatternParams {
+            date_utc,
+            expand: Some(vec![ResponseExpand::Run, ResponseExpand::Route]),
+            ..Default::default()
+        };
+
+        // Runs departing the origin, and (for transfers) runs departing the destination
+        let mut runs = Vec::new();
+        let origin_departures = self.departures(origin, departures_params.clone()).await?;
+        let from_origin = unique_runs(origin.route_type, &origin_departures);
+        runs.extend(from_origin.iter().cloned());
+        let mut to_destination = Vec::new();
+        if params.allow_transfer && origin.route_type == destination.route_type {
+            let destination_departures = self.departures(destination, departures_params).await?;
+            to_destination = unique_runs(destination.route_type, &destination_departures);
+            runs.extend(to_destination.iter().cloned());
+        }
+        runs.sort();
+        runs.dedup();
+
+        let patterns: BTreeMap<Run, PatternResponse> = futures_util::stream::iter(runs)
+            .map(|run| {
+                let params = pattern_params.clone();
+                async move {
+                    let result = self.pattern(&run, params).await;
+                    (run, result)
+                }
+            })
+            .buffered(params.concurrency.max(1))
+            .filter_map(|(run, result)| async move { Some((run, result.ok()?)) })
+            .collect()
+            .await;
+        let collect = |runs: Vec<Run>| -> Vec<Pattern> {
+            runs.into_iter()
+                .filter_map(|run| {
+                    let response = patterns.get(&run)?.clone();
+                    Some(Pattern::new(run, response))
+                })
+                .collect()
+        };
+        Ok(itineraries(
+            origin,
+            destination,
+            departure_time,
+            &collect(from_origin),
+            &collect(to_destination),
+            &params,
+        ))
+    }
+}
+
+/// Internal helper to list the runs in a departures response, in departure order
+fn unique_runs(route_type: RouteType, response: &DeparturesResponse) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for departure in &response.departures {
+        let run = Run {
+            route_type,
+            id: departure.run_ref.clone(),
+        };
+        if !runs.contains(&run) {
+            runs.push(run);
+        }
+    }
+    runs
+}
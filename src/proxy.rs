@@ -0,0 +1,212 @@
+use crate::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Default for [ProxyParams::cache_capacity]
+const DEFAULT_CACHE_CAPACITY: usize = 1000;
+
+/// Limit on the rate of requests forwarded upstream by a [SigningProxy]
+///
+/// This is a token bucket: up to `requests` can be made in a burst, and they're regained
+/// evenly over `per`.
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimit {
+    /// Maximum number of requests in a burst
+    pub requests: u32,
+    /// Time taken to regain all `requests`
+    pub per: Duration,
+}
+
+/// Parameters for [SigningProxy::new]
+#[derive(Clone, Debug, Default)]
+pub struct ProxyParams {
+    /// Cache successful responses for this long (default = no caching)
+    pub cache_ttl: Option<Duration>,
+    /// Maximum number of cached responses (default = 1000)
+    pub cache_capacity: Option<usize>,
+    /// Limit the rate of requests forwarded upstream, others fail with `429 Too Many Requests`
+    /// (default = no limit)
+    pub rate_limit: Option<RateLimit>,
+}
+
+/// Response from [SigningProxy::handle]
+#[derive(Clone, Debug)]
+pub struct ProxyResponse {
+    /// HTTP status, as returned upstream
+    pub status: StatusCode,
+    /// JSON body, as returned upstream
+    pub body: String,
+}
+impl ProxyResponse {
+    /// Internal helper to build an error in the same shape as the API's
+    fn error(status: StatusCode, message: &str) -> Self {
+        Self {
+            status,
+            body: serde_json::json!({ "message": message }).to_string(),
+        }
+    }
+}
+
+/// Internal state of the token bucket for [ProxyParams::rate_limit]
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// HTTP proxy which signs requests with server-side credentials, so clients never hold the key
+///
+/// Clients make unsigned requests for `/v3/...` paths (any `devid` or `signature` they send is
+/// dropped). Each is signed with the credentials of the wrapped [PTV] and forwarded to its base
+/// URL, see [PTV::with_base_url]. Use [SigningProxy::router] to serve it with `axum`.
+pub struct SigningProxy<HttpClient: PTVHttpClient> {
+    ptv: PTV<HttpClient>,
+    params: ProxyParams,
+    cache: Mutex<HashMap<String, (Instant, ProxyResponse)>>,
+    bucket: Mutex<Bucket>,
+}
+
+impl<HttpClient: PTVHttpClient> SigningProxy<HttpClient> {
+    /// Proxy requests using the credentials and HTTP client of `ptv`
+    pub fn new(ptv: PTV<HttpClient>, params: ProxyParams) -> Self {
+        let tokens = params.rate_limit.map_or(0.0, |limit| limit.requests as f64);
+        Self {
+            ptv,
+            params,
+            cache: Mutex::new(HashMap::new()),
+            bucket: Mutex::new(Bucket {
+                tokens,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Sign and forward an unsigned request path and query (i.e. `/v3/route_types`)
+    pub async fn handle(&self, path_and_query: &str) -> ProxyResponse {
+        let (path, query) = path_and_query
+            .split_once('?')
+            .unwrap_or((path_and_query, ""));
+        let query = strip_credentials(query);
+
+        // Only the path is taken from the request, so it can't name another host (i.e. `//host/v3/`)
+        let mut url = self.ptv.base.clone();
+        url.set_path(path);
+        if !url.path().starts_with("/v3/") {
+            return ProxyResponse::error(StatusCode::NOT_FOUND, "Not found");
+        }
+        let key = format!("{}?{query}", url.path());
+
+        if let Some(response) = self.cached(&key) {
+            return response;
+        }
+        if !self.take_token() {
+            return ProxyResponse::error(StatusCode::TOO_MANY_REQUESTS, "Too many requests");
+        }
+
+        url.set_query(Some(&query));
+        let url = match self.ptv.sign_url(url).await {
+            Ok(url) => url,
+            Err(error) => {
+                return ProxyResponse::error(StatusCode::INTERNAL_SERVER_ERROR, &error.to_string())
+            }
+        };
+        let response = match self.ptv.http_client.api_get::<serde_json::Value>(url).await {
+            Ok(json) => ProxyResponse {
+                status: StatusCode::OK,
+                body: json.to_string(),
+            },
+            Err(Error::API(status, error)) => ProxyResponse {
+                status,
+                body: serde_json::to_string(&error).unwrap_or_default(),
+            },
+            Err(Error::HTTP(status, body)) => ProxyResponse { status, body },
+            Err(error) => ProxyResponse::error(StatusCode::BAD_GATEWAY, &error.to_string()),
+        };
+
+        if response.status.is_success() {
+            self.store(key, &response);
+        }
+        response
+    }
+
+    /// Internal helper to get a response from the cache, if it's still fresh
+    fn cached(&self, key: &str) -> Option<ProxyResponse> {
+        let ttl = self.params.cache_ttl?;
+        let cache = self.cache.lock().expect("Proxy cache poisoned");
+        let (stored, response) = cache.get(key)?;
+        (stored.elapsed() < ttl).then(|| response.clone())
+    }
+
+    /// Internal helper to add a response to the cache, making room if it's full
+    fn store(&self, key: String, response: &ProxyResponse) {
+        let Some(ttl) = self.params.cache_ttl else {
+            return;
+        };
+        let capacity = self.params.cache_capacity.unwrap_or(DEFAULT_CACHE_CAPACITY);
+        let mut cache = self.cache.lock().expect("Proxy cache poisoned");
+        if cache.len() >= capacity {
+            cache.retain(|_, (stored, _)| stored.elapsed() < ttl);
+        }
+        while cache.len() >= capacity {
+            let Some(oldest) = cache
+                .iter()
+                .min_by_key(|(_, (stored, _))| *stored)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            cache.remove(&oldest);
+        }
+        if capacity > 0 {
+            cache.insert(key, (Instant::now(), response.clone()));
+        }
+    }
+
+    /// Internal helper to take a token from the rate limit bucket, returning false if it's empty
+    fn take_token(&self) -> bool {
+        let Some(limit) = self.params.rate_limit else {
+            return true;
+        };
+        let mut bucket = self.bucket.lock().expect("Proxy rate limit poisoned");
+        let now = Instant::now();
+        let regained =
+            (now - bucket.updated).as_secs_f64() / limit.per.as_secs_f64() * limit.requests as f64;
+        bucket.tokens = (bucket.tokens + regained).min(limit.requests as f64);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<HttpClient: PTVHttpClient + Send + Sync + 'static> SigningProxy<HttpClient> {
+    /// An `axum` router serving `GET /v3/...` through this proxy
+    pub fn router(self) -> axum::Router {
+        use axum::extract::State;
+        use axum::http::{header, StatusCode, Uri};
+        use std::sync::Arc;
+
+        async fn proxy<HttpClient: PTVHttpClient + Send + Sync + 'static>(
+            State(proxy): State<Arc<SigningProxy<HttpClient>>>,
+            uri: Uri,
+        ) -> impl axum::response::IntoResponse {
+            let path_and_query = uri.path_and_query().map_or("/", |pq| pq.as_str());
+            let response = proxy.handle(path_and_query).await;
+            let status =
+                StatusCode::from_u16(response.status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+            (
+                status,
+                [(header::CONTENT_TYPE, "application/json")],
+                response.body,
+            )
+        }
+
+        axum::Router::new()
+            .route("/v3/{*path}", axum::routing::get(proxy::<HttpClient>))
+            .with_state(Arc::new(self))
+    }
+}
@@ -15,7 +15,7 @@ impl PTVHttpClient for ::reqwest::Client {
             let status = response.status();
             let text = response.text().await?;
             if status.is_success() {
-                Ok(serde_json::from_str(&text)?)
+                parse_json(&text)
             } else if let Ok(error) = serde_json::from_str(&text) {
                 Err(Error::API(status, error))
             } else {
@@ -39,11 +39,6 @@ impl PTV<::reqwest::Client> {
             .user_agent(user_agent.to_string())
             .build()
             .expect("Failed to build reqwest Client for PTV API");
-        Self {
-            http_client: client,
-            devid: devid.to_string(),
-            key: key.to_string(),
-            base: Url::parse(BASE_URL).expect("Failed to create base URL"),
-        }
+        Self::with_client(client, devid, key)
     }
 }
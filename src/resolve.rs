@@ -0,0 +1,155 @@
+use crate::*;
+use std::str::FromStr;
+
+/// Options for [PTV::resolve_stop] and [rank_stops]
+#[derive(Clone, Debug)]
+pub struct ResolveParams {
+    /// Route types to prefer when names are similar, most preferred first (default = no preference)
+    pub preferred_route_types: Vec<RouteType>,
+    /// Minimum score for a stop to be a candidate (default = 0.75)
+    pub min_score: f64,
+    /// Candidates scoring within this much of the best are ambiguous (default = 0.05)
+    pub ambiguity_margin: f64,
+}
+impl Default for ResolveParams {
+    fn default() -> Self {
+        Self {
+            preferred_route_types: Vec::new(),
+            min_score: 0.75,
+            ambiguity_margin: 0.05,
+        }
+    }
+}
+
+/// A stop which might be the one being looked for
+#[derive(Clone, Debug)]
+pub struct StopCandidate {
+    /// The stop
+    pub stop: StopDetails,
+    /// Name similarity between 0 and 1, plus up to 0.1 for a preferred route type
+    pub score: f64,
+}
+
+/// Result of [PTV::resolve_stop]
+#[derive(Clone, Debug)]
+pub enum StopResolution {
+    /// The text was a stop literal such as `Stop:Train/1071`
+    Literal(Stop),
+    /// One stop matched clearly better than the others
+    Found(StopCandidate),
+    /// Several stops matched about as well, best first
+    Ambiguous(Vec<StopCandidate>),
+    /// Nothing matched well enough
+    NotFound,
+}
+impl StopResolution {
+    /// The resolved stop, unless it was ambiguous or not found
+    pub fn stop(&self) -> Option<Stop> {
+        match self {
+            Self::Literal(stop) => Some(stop.clone()),
+            Self::Found(candidate) => Some(candidate.stop.stop()),
+            Self::Ambiguous(_) | Self::NotFound => None,
+        }
+    }
+}
+
+/// Internal helper to split a stop name (or search) into words for comparison
+///
+/// Lowercases, drops a trailing suburb ("Smith St (Fitzroy)", "Flinders St, Melbourne"),
+/// and expands abbreviations. "St" becomes "Street" unless it's leading ("St Kilda").
+fn words(name: &str) -> Vec<String> {
+    let name = name.split([',', '(']).next().unwrap_or(name);
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .enumerate()
+        .map(|(index, word)| {
+            let word = word.to_lowercase();
+            match word.as_str() {
+                "st" if index > 0 => "street".to_string(),
+                "rd" => "road".to_string(),
+                "av" | "ave" => "avenue".to_string(),
+                "stn" => "station".to_string(),
+                _ => word,
+            }
+        })
+        .collect()
+}
+
+/// Internal helper to join words, without a trailing "Station" or "Railway Station"
+fn join_words(mut words: &[String]) -> String {
+    while let [rest @ .., last] = words {
+        if rest.is_empty() || !matches!(last.as_str(), "station" | "railway") {
+            break;
+        }
+        words = rest;
+    }
+    words.join(" ")
+}
+
+/// Internal helper to score a stop against the words of a query
+fn score(query: &[String], stop: &StopDetails, params: &ResolveParams) -> f64 {
+    let name = join_words(&words(&stop.stop_name));
+    let mut similarity = strsim::jaro_winkler(&join_words(query), &name);
+    // Allow the suburb to be given after the name, e.g. "Glenferrie Station Hawthorn"
+    let suburb = words(&stop.stop_suburb);
+    if !suburb.is_empty() && query.len() > suburb.len() && query.ends_with(&suburb) {
+        let without = join_words(&query[..query.len() - suburb.len()]);
+        similarity = similarity.max(strsim::jaro_winkler(&without, &name));
+    }
+    let preferred = &params.preferred_route_types;
+    let bonus = match preferred.iter().position(|rt| *rt == stop.route_type) {
+        Some(index) => 0.1 * (preferred.len() - index) as f64 / preferred.len() as f64,
+        None => 0.0,
+    };
+    similarity + bonus
+}
+
+/// Rank stops by how well they match some text, see [PTV::resolve_stop]
+pub fn rank_stops(
+    text: &str,
+    stops: impl IntoIterator<Item = StopDetails>,
+    params: &ResolveParams,
+) -> StopResolution {
+    let query = words(text);
+    let mut candidates: Vec<StopCandidate> = stops
+        .into_iter()
+        .map(|stop| StopCandidate {
+            score: score(&query, &stop, params),
+            stop,
+        })
+        .filter(|candidate| candidate.score >= params.min_score)
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates.dedup_by(|a, b| a.stop.stop() == b.stop.stop());
+
+    let Some(best) = candidates.first().map(|candidate| candidate.score) else {
+        return StopResolution::NotFound;
+    };
+    candidates.retain(|candidate| best - candidate.score < params.ambiguity_margin);
+    if candidates.len() == 1 {
+        StopResolution::Found(candidates.remove(0))
+    } else {
+        StopResolution::Ambiguous(candidates)
+    }
+}
+
+impl<HTTPClient: PTVHttpClient> PTV<HTTPClient> {
+    /// Turn some user supplied text into a stop
+    ///
+    /// The text can be a stop literal (e.g. `Stop:Train/1071`), otherwise the stops returned by
+    /// [PTV::search] are ranked by [rank_stops].
+    pub async fn resolve_stop(&self, text: &str, params: ResolveParams) -> Result<StopResolution> {
+        if let Ok(stop) = Stop::from_str(text) {
+            return Ok(StopResolution::Literal(stop));
+        }
+        let search_term = text.split([',', '(']).next().unwrap_or(text).trim();
+        let search_params = SearchParams {
+            include_outlets: Some(false),
+            match_stop_by_suburb: Some(false),
+            match_route_by_suburb: Some(false),
+            ..Default::default()
+        };
+        let response = self.search(search_term, search_params).await?;
+        Ok(rank_stops(text, response.stops, &params))
+    }
+}
@@ -0,0 +1,155 @@
+use crate::*;
+
+/// Why a URL failed [verify_signature]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    /// There's no `signature` query parameter
+    Missing,
+    /// There's more than one `signature` query parameter
+    Duplicated,
+    /// The `signature` isn't a hex encoded HMAC-SHA1 (the value given)
+    Malformed(String),
+    /// There's no `devid` query parameter
+    MissingDevId,
+    /// The signature is well formed, but doesn't match the URL and key
+    Mismatch,
+}
+impl std::error::Error for SignatureError {}
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "URL has no signature"),
+            Self::Duplicated => write!(f, "URL has more than one signature"),
+            Self::Malformed(signature) => write!(f, "Signature '{signature}' is malformed"),
+            Self::MissingDevId => write!(f, "URL has no devid"),
+            Self::Mismatch => write!(f, "Signature doesn't match"),
+        }
+    }
+}
+
+/// Internal helper to compute the signature of a path and query (i.e. `/v3/route_types`, `devid=0`)
+///
+/// This is shared by signing and verification, so they can't disagree on encoding.
+pub(crate) fn hmac_signature(key: &[u8], path: &str, query: &str) -> HmacSha1 {
+    HmacSha1::new_from_slice(key)
+        .expect("Failed to initialize HMAC")
+        .chain_update(path)
+        .chain_update(b"?")
+        .chain_update(query)
+}
+
+/// Check the signature of a URL, as signed by [PTV] with the developer `key`
+///
+/// The HMAC-SHA1 is recomputed over the path and query exactly as they appear in the URL
+/// (excluding the `signature` parameter), and compared in constant time. This is intended for
+/// servers receiving signed requests, such as mock PTV servers or proxies.
+pub fn verify_signature(url: &Url, key: &str) -> std::result::Result<(), SignatureError> {
+    let query = url.query().unwrap_or_default();
+    let (signatures, rest): (Vec<&str>, Vec<&str>) = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .partition(|pair| pair.split('=').next() == Some("signature"));
+    let signature = match signatures.as_slice() {
+        [] => return Err(SignatureError::Missing),
+        [signature] => signature.split_once('=').map_or("", |(_, value)| value),
+        _ => return Err(SignatureError::Duplicated),
+    };
+    if !rest
+        .iter()
+        .any(|pair| pair.split('=').next() == Some("devid"))
+    {
+        return Err(SignatureError::MissingDevId);
+    }
+
+    let malformed = || SignatureError::Malformed(signature.to_string());
+    if signature.len() != 40 || !signature.is_ascii() {
+        return Err(malformed());
+    }
+    let bytes = (0..signature.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&signature[i..i + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| malformed())?;
+
+    hmac_signature(key.as_bytes(), url.path(), &rest.join("&"))
+        .verify_slice(&bytes)
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+/// Internal helper to remove the `devid` and `signature` parameters from a query, keeping its encoding
+pub(crate) fn strip_credentials(query: &str) -> String {
+    let query: Vec<&str> = query
+        .split('&')
+        .filter(|pair| {
+            let name = pair.split('=').next().unwrap_or_default();
+            !pair.is_empty() && name != "devid" && name != "signature"
+        })
+        .collect();
+    query.join("&")
+}
+
+/// A URL with the value of its `signature` hidden, for logging
+pub fn redacted_url(url: &Url) -> String {
+    let Some(query) = url.query() else {
+        return url.to_string();
+    };
+    let query: Vec<&str> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some(("signature", _)) => "signature=REDACTED",
+            _ => pair,
+        })
+        .collect();
+    let mut url = url.clone();
+    url.set_query(Some(&query.join("&")));
+    url.to_string()
+}
+
+/// Signs requests to the PTV API, see [PTV::with_signer]
+///
+/// The default is [HmacSigner], which holds the developer key in memory. Other implementations
+/// can keep the key out of the process entirely, i.e. by asking a separate signing service or
+/// looking up pre-signed URLs. A signer which fails returns an error, which fails the request
+/// being signed before anything is sent.
+pub trait Signer: Send + Sync {
+    /// Hex encoded signature of a request path and query (which includes the `devid`)
+    fn sign(&self, path: &str, query: &str) -> FutureResult<String>;
+}
+
+/// Signs requests with HMAC-SHA1 using the developer key, as described by PTV
+///
+/// The key isn't shown by `Debug`.
+#[derive(Clone)]
+pub struct HmacSigner {
+    key: String,
+}
+impl HmacSigner {
+    /// Sign with a developer key (i.e. `"00000000-0000-0000-0000-000000000000"`)
+    pub fn new(key: impl ToString) -> Self {
+        Self {
+            key: key.to_string(),
+        }
+    }
+    /// Check the signature of a URL, see [verify_signature]
+    pub fn verify(&self, url: &Url) -> std::result::Result<(), SignatureError> {
+        verify_signature(url, &self.key)
+    }
+}
+impl Signer for HmacSigner {
+    fn sign(&self, path: &str, query: &str) -> FutureResult<String> {
+        let signature = hmac_signature(self.key.as_bytes(), path, query).finalize();
+        let signature = signature
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect();
+        Box::pin(std::future::ready(Ok(signature)))
+    }
+}
+impl std::fmt::Debug for HmacSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("HmacSigner")
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
@@ -3,22 +3,24 @@ use std::collections::HashMap;
 use std::future::ready;
 use std::str::FromStr;
 
-const FLINDERS: Stop = Stop {
+/// Stop of the `departures_0_1181` fixture
+const SOUTHERN_CROSS: Stop = Stop {
     route_type: RouteType::TRAIN,
-    id: StopId::new(1071),
+    id: StopId::new(1181),
 };
 
 /// Build a PTV instance which is testable
 struct MockClient(HashMap<String, Result<String>>);
 impl PTVHttpClient for MockClient {
     fn api_get<T: DeserializeOwned + Send + 'static>(&self, url: Url) -> FutureResult<T> {
+        let key = cassette_key(&url);
         let result = self
             .0
             .get(url.as_str())
-            .or_else(|| self.0.get("*"))
-            .expect(&format!("Failed to get URL {url}"));
+            .or_else(|| self.0.get(&key))
+            .unwrap_or_else(|| panic!("Failed to get URL {key}"));
         let result = match result {
-            Ok(json) => serde_json::from_str(&json).map_err(Error::from),
+            Ok(json) => parse_json(json),
             Err(err) => Err(err.clone()),
         };
         Box::pin(ready(result))
@@ -26,12 +28,34 @@ impl PTVHttpClient for MockClient {
 }
 impl PTV<MockClient> {
     fn mock(mapping: HashMap<String, Result<String>>) -> PTV<MockClient> {
-        PTV {
-            http_client: MockClient(mapping),
-            devid: "0".to_string(),
-            key: "00000000-0000-0000-0000-000000000000".to_string(),
-            base: Url::parse(BASE_URL).unwrap(),
-        }
+        PTV::with_mock_client(MockClient(mapping))
+    }
+}
+impl<Client: PTVHttpClient> PTV<Client> {
+    fn with_mock_client(http_client: Client) -> PTV<Client> {
+        PTV::with_client(http_client, "0", "00000000-0000-0000-0000-000000000000")
+    }
+}
+
+/// Mock client which returns a sequence of responses, regardless of URL
+#[cfg(feature = "watch")]
+struct SequenceClient(std::sync::Mutex<std::collections::VecDeque<Result<String>>>);
+#[cfg(feature = "watch")]
+impl SequenceClient {
+    fn new(responses: impl IntoIterator<Item = Result<String>>) -> Self {
+        Self(std::sync::Mutex::new(responses.into_iter().collect()))
+    }
+}
+#[cfg(feature = "watch")]
+impl PTVHttpClient for SequenceClient {
+    fn api_get<T: DeserializeOwned + Send + 'static>(&self, url: Url) -> FutureResult<T> {
+        let result = self
+            .0
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| panic!("No more responses for URL {url}"));
+        Box::pin(ready(result.and_then(|json| parse_json(&json))))
     }
 }
 
@@ -43,7 +67,7 @@ async fn test_departures_params() {
 
     // No parameters
     assert_eq!(
-        ptv.build_url("v3/departures/route_type/0/stop/1071", ()).to_string(),
+        ptv.build_url("v3/departures/route_type/0/stop/1071", ()).await.unwrap().to_string(),
         "https://timetableapi.ptv.vic.gov.au/v3/departures/route_type/0/stop/1071?devid=0&signature=42DB35865957B23483EB372990105E9BC57F90CD"
     );
 
@@ -52,7 +76,7 @@ async fn test_departures_params() {
         ..Default::default()
     };
     assert_eq!(
-        ptv.build_url("v3/departures/route_type/0/stop/1071", params).to_string(),
+        ptv.build_url("v3/departures/route_type/0/stop/1071", params).await.unwrap().to_string(),
         "https://timetableapi.ptv.vic.gov.au/v3/departures/route_type/0/stop/1071?devid=0&signature=42DB35865957B23483EB372990105E9BC57F90CD"
     );
 
@@ -61,7 +85,7 @@ async fn test_departures_params() {
         ..Default::default()
     };
     assert_eq!(
-        ptv.build_url("v3/departures/route_type/0/stop/1071", params).to_string(),
+        ptv.build_url("v3/departures/route_type/0/stop/1071", params).await.unwrap().to_string(),
         "https://timetableapi.ptv.vic.gov.au/v3/departures/route_type/0/stop/1071?include_cancelled=true&devid=0&signature=E806A7A79C6C5012B409DA3BE153D2A640F449CD"
     );
 
@@ -71,7 +95,7 @@ async fn test_departures_params() {
         ..Default::default()
     };
     assert_eq!(
-        ptv.build_url("v3/departures/route_type/0/stop/1071", params).to_string(),
+        ptv.build_url("v3/departures/route_type/0/stop/1071", params).await.unwrap().to_string(),
         "https://timetableapi.ptv.vic.gov.au/v3/departures/route_type/0/stop/1071?date_utc=1970-01-01T00%3A00%3A00Z&devid=0&signature=B6A285B7184C0AA63D5BDA533314B6506523AAD2"
     );
 
@@ -80,7 +104,7 @@ async fn test_departures_params() {
         ..Default::default()
     };
     assert_eq!(
-        ptv.build_url("v3/departures/route_type/0/stop/1071", params).to_string(),
+        ptv.build_url("v3/departures/route_type/0/stop/1071", params).await.unwrap().to_string(),
         "https://timetableapi.ptv.vic.gov.au/v3/departures/route_type/0/stop/1071?expand=Route&devid=0&signature=59F8316A7E7B06E44712B4D6CF59B8587F7D9218"
     );
 
@@ -89,7 +113,7 @@ async fn test_departures_params() {
         ..Default::default()
     };
     assert_eq!(
-        ptv.build_url("v3/departures/route_type/0/stop/1071", params).to_string(),
+        ptv.build_url("v3/departures/route_type/0/stop/1071", params).await.unwrap().to_string(),
         "https://timetableapi.ptv.vic.gov.au/v3/departures/route_type/0/stop/1071?expand=Route&expand=Run&devid=0&signature=88DCDBA082D1B9AD22B9D0722D8C6486186CF7D0"
     );
 }
@@ -98,9 +122,13 @@ async fn test_departures_params() {
 #[tokio::test]
 async fn test_train() {
     let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
-    let ptv = PTV::mock(HashMap::from([("*".to_string(), Ok(data))]));
+    let stop = Stop::from_str("Stop:Train/1181").unwrap();
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/departures/route_type/0/stop/1181".to_string(),
+        Ok(data),
+    )]));
     let result = ptv
-        .departures(&FLINDERS, Default::default())
+        .departures(&stop, Default::default())
         .await
         .expect("Parsing failed");
     eprintln!("{:#?}", result);
@@ -110,9 +138,13 @@ async fn test_train() {
 #[tokio::test]
 async fn test_tram() {
     let data = std::fs::read_to_string("testdata/departures_1_2479.json").unwrap();
-    let ptv = PTV::mock(HashMap::from([("*".to_string(), Ok(data))]));
+    let stop = Stop::from_str("Stop:Tram/2479").unwrap();
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/departures/route_type/1/stop/2479".to_string(),
+        Ok(data),
+    )]));
     let result = ptv
-        .departures(&FLINDERS, Default::default())
+        .departures(&stop, Default::default())
         .await
         .expect("Parsing failed");
     eprintln!("{:#?}", result);
@@ -122,9 +154,13 @@ async fn test_tram() {
 #[tokio::test]
 async fn test_bus() {
     let data = std::fs::read_to_string("testdata/departures_2_17805.json").unwrap();
-    let ptv = PTV::mock(HashMap::from([("*".to_string(), Ok(data))]));
+    let stop = Stop::from_str("Stop:Bus/17805").unwrap();
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/departures/route_type/2/stop/17805".to_string(),
+        Ok(data),
+    )]));
     let result = ptv
-        .departures(&FLINDERS, Default::default())
+        .departures(&stop, Default::default())
         .await
         .expect("Parsing failed");
     eprintln!("{:#?}", result);
@@ -134,9 +170,13 @@ async fn test_bus() {
 #[tokio::test]
 async fn test_vline() {
     let data = std::fs::read_to_string("testdata/departures_3_1181.json").unwrap();
-    let ptv = PTV::mock(HashMap::from([("*".to_string(), Ok(data))]));
+    let stop = Stop::from_str("Stop:Vline/1181").unwrap();
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/departures/route_type/3/stop/1181".to_string(),
+        Ok(data),
+    )]));
     let result = ptv
-        .departures(&FLINDERS, Default::default())
+        .departures(&stop, Default::default())
         .await
         .expect("Parsing failed");
     eprintln!("{:#?}", result);
@@ -158,11 +198,11 @@ fn test_parsing_stops() {
     ];
     for (s, route_type, id) in tests {
         let id = StopId::new(id);
-        let parsed = Stop::from_str(s).expect(&format!("Failed to parse: {s}"));
+        let parsed = Stop::from_str(s).unwrap_or_else(|_| panic!("Failed to parse: {s}"));
         assert_eq!(parsed, Stop { route_type, id }, "Failed to parse: {s}");
 
         let rt_string = parsed.to_string();
-        let rt = Stop::from_str(&rt_string).expect(&format!("Failed to round trip: {s}"));
+        let rt = Stop::from_str(&rt_string).unwrap_or_else(|_| panic!("Failed to round trip: {s}"));
         assert_eq!(parsed, rt, "Failed to round trip: {s}");
     }
 }
@@ -183,11 +223,2079 @@ fn test_parsing_routes() {
     ];
     for (s, route_type, id) in tests {
         let id = RouteId::new(id);
-        let parsed = Route::from_str(s).expect(&format!("Failed to parse: {s}"));
+        let parsed = Route::from_str(s).unwrap_or_else(|_| panic!("Failed to parse: {s}"));
         assert_eq!(parsed, Route { route_type, id }, "Failed to parse: {s}");
 
         let rt_string = parsed.to_string();
-        let rt = Route::from_str(&rt_string).expect(&format!("Failed to round trip: {s}"));
+        let rt =
+            Route::from_str(&rt_string).unwrap_or_else(|_| panic!("Failed to round trip: {s}"));
         assert_eq!(parsed, rt, "Failed to round trip: {s}");
     }
 }
+
+/// Remove `null` values from objects, PTV isn't consistent about omitting vs. nulling fields
+fn strip_nulls(value: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k, strip_nulls(v)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(strip_nulls).collect()),
+        other => other,
+    }
+}
+
+/// Test that responses serialize back to JSON equivalent to what was received
+#[test]
+fn test_serialize_round_trip() {
+    for path in [
+        "testdata/departures_0_1181.json",
+        "testdata/departures_1_2479.json",
+        "testdata/departures_2_17805.json",
+        "testdata/departures_3_1181.json",
+    ] {
+        let data = std::fs::read_to_string(path).unwrap();
+        let response: DeparturesResponse = serde_json::from_str(&data).unwrap();
+        let serialized = serde_json::to_string(&response).unwrap();
+
+        let original: serde_json::Value = serde_json::from_str(&data).unwrap();
+        let round_trip: serde_json::Value = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(
+            strip_nulls(original),
+            strip_nulls(round_trip),
+            "Round trip mismatch: {path}"
+        );
+    }
+}
+
+/// Test that unknown fields are kept by default, and rejected in strict mode
+#[tokio::test]
+async fn test_unknown_fields() {
+    let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let mut json: serde_json::Value = serde_json::from_str(&data).unwrap();
+    json["directions"]["1"]["route_direction_description"] = serde_json::json!("");
+    json["runs"]["952051"]["interchange"] = serde_json::Value::Null;
+    let data = json.to_string();
+
+    // Lenient (default) mode keeps the fields
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/departures/route_type/0/stop/1181".to_string(),
+        Ok(data.clone()),
+    )]));
+    let result = ptv
+        .departures(&SOUTHERN_CROSS, Default::default())
+        .await
+        .unwrap();
+    let direction = &result.directions[&DirectionId::new(1)];
+    assert_eq!(
+        direction.extra["route_direction_description"],
+        serde_json::json!("")
+    );
+    assert_eq!(
+        result.unknown_fields(),
+        [
+            "runs.952051.interchange",
+            "directions.1.route_direction_description"
+        ]
+    );
+    let round_trip: serde_json::Value = serde_json::to_value(&result).unwrap();
+    assert_eq!(
+        round_trip["directions"]["1"]["route_direction_description"],
+        serde_json::json!("")
+    );
+
+    // Strict mode names the fields
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/departures/route_type/0/stop/1181".to_string(),
+        Ok(data),
+    )]))
+    .with_strict(true);
+    match ptv.departures(&SOUTHERN_CROSS, Default::default()).await {
+        Err(Error::UnknownFields(fields)) => {
+            assert_eq!(
+                fields,
+                [
+                    "runs.952051.interchange",
+                    "directions.1.route_direction_description"
+                ]
+            )
+        }
+        other => panic!("Expected unknown fields error, got {other:?}"),
+    }
+
+    // Strict mode is fine with a response that's fully modelled
+    let data = std::fs::read_to_string("testdata/departures_3_1181.json").unwrap();
+    let vline = Stop::from_str("Stop:Vline/1181").unwrap();
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/departures/route_type/3/stop/1181".to_string(),
+        Ok(data),
+    )]))
+    .with_strict(true);
+    ptv.departures(&vline, Default::default()).await.unwrap();
+}
+
+/// Test that parse errors name the field which failed
+#[tokio::test]
+async fn test_parse_error_path() {
+    let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let mut json: serde_json::Value = serde_json::from_str(&data).unwrap();
+    json["departures"][1]["at_platform"] = serde_json::json!("yes");
+
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/departures/route_type/0/stop/1181".to_string(),
+        Ok(json.to_string()),
+    )]));
+    match ptv.departures(&SOUTHERN_CROSS, Default::default()).await {
+        Err(Error::JsonParseError(message)) => {
+            assert!(
+                message.starts_with("departures[1].at_platform:"),
+                "{message}"
+            )
+        }
+        other => panic!("Expected parse error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_geo() {
+    let flinders = LatLon::new(-37.8183, 144.966965);
+    let southern_cross = LatLon::new(-37.818306, 144.952423);
+    let box_hill = LatLon::new(-37.8188, 145.1218);
+
+    assert!((flinders.distance(&southern_cross) - 1277.4).abs() < 1.0);
+    assert!((flinders.distance(&box_hill) - 13600.7).abs() < 1.0);
+    assert_eq!(flinders.distance(&flinders), 0.0);
+    assert!((flinders.bearing(&southern_cross) - 270.0).abs() < 0.1);
+    assert!((flinders.bearing(&box_hill) - 90.3).abs() < 0.1);
+
+    assert!(flinders.within_radius(&southern_cross, 1500.0));
+    assert!(!flinders.within_radius(&box_hill, 1500.0));
+
+    let bounds = flinders.bounding_box(1500.0);
+    assert!(bounds.contains(&southern_cross));
+    assert!(!bounds.contains(&box_hill));
+    assert!((bounds.center().distance(&flinders)) < 0.01);
+    // Edges of the box are the radius away from the centre
+    let north = LatLon::new(bounds.north_east.latitude, flinders.longitude);
+    let east = LatLon::new(flinders.latitude, bounds.north_east.longitude);
+    assert!((flinders.distance(&north) - 1500.0).abs() < 0.1);
+    assert!((flinders.distance(&east) - 1500.0).abs() < 0.1);
+
+    let bounds = BoundingBox::from_points([flinders, southern_cross, box_hill]).unwrap();
+    assert_eq!(bounds.south_west, LatLon::new(-37.8188, 144.952423));
+    assert_eq!(bounds.north_east, LatLon::new(-37.8183, 145.1218));
+    assert!(BoundingBox::from_points([]).is_none());
+
+    // Accessors on response types
+    let data = std::fs::read_to_string("testdata/departures_2_17805.json").unwrap();
+    let response: DeparturesResponse = serde_json::from_str(&data).unwrap();
+    let stop = &response.stops[&StopId::new(17805)];
+    assert_eq!(stop.location(), box_hill);
+    let run = &response.runs[&RunRef::new("5-903-mjp-1.10.R-3-21".to_string())];
+    let position = run.vehicle_position.as_ref().unwrap().location().unwrap();
+    assert!(position.within_radius(&stop.location(), 100.0));
+
+    let params = SearchParams::default().with_location(flinders, Some(500.0));
+    assert_eq!(params.location(), Some(flinders));
+    assert_eq!(params.max_distance, Some(500.0));
+}
+
+#[test]
+fn test_geopath() {
+    let data = std::fs::read_to_string("testdata/departures_1_2479.json").unwrap();
+    let response: DeparturesResponse = serde_json::from_str(&data).unwrap();
+    let route = &response.routes[&RouteId::new(1881)];
+    let geopath = route.geopath.as_ref().unwrap();
+    assert_eq!(geopath.len(), 2);
+    assert_eq!(geopath[0].direction_id, DirectionId::new(2));
+    assert_eq!(geopath[0].paths[0].0.len(), 5);
+    assert_eq!(
+        geopath[0].paths[0].0[3],
+        LatLon::new(-37.8133163, 144.96556)
+    );
+    assert_eq!(geopath[1].paths.len(), 2);
+
+    // Separators are flexible, odd numbers of values are not
+    let polyline: Polyline = "-37.1,144.1, -37.2 ,144.2".parse().unwrap();
+    assert_eq!(
+        polyline.0,
+        [LatLon::new(-37.1, 144.1), LatLon::new(-37.2, 144.2)]
+    );
+    assert_eq!(polyline.to_string(), "-37.1, 144.1 -37.2, 144.2");
+    assert!("-37.1, 144.1 -37.2".parse::<Polyline>().is_err());
+    assert!("-37.1, north".parse::<Polyline>().is_err());
+
+    let geojson = route.to_geojson();
+    assert_eq!(geojson["type"], "FeatureCollection");
+    let features = geojson["features"].as_array().unwrap();
+    assert_eq!(features.len(), 2);
+    assert_eq!(features[0]["geometry"]["type"], "MultiLineString");
+    assert_eq!(
+        features[0]["geometry"]["coordinates"][0][3],
+        serde_json::json!([144.96556, -37.8133163])
+    );
+    assert_eq!(features[1]["properties"]["direction_id"], 1);
+    assert_eq!(features[1]["properties"]["route_number"], "1");
+
+    // Routes without geopaths are empty
+    let route = &response.routes[&RouteId::new(724)];
+    assert_eq!(route.to_geojson()["features"], serde_json::json!([]));
+
+    let stop = &response.stops[&StopId::new(2479)];
+    let geojson = stop.to_geojson();
+    assert_eq!(geojson["type"], "Feature");
+    assert_eq!(geojson["id"], "Stop:Tram/2479");
+    assert_eq!(
+        geojson["geometry"]["coordinates"],
+        serde_json::json!([144.96556, -37.8133163])
+    );
+    assert_eq!(
+        geojson["properties"]["stop_name"],
+        "Bourke St/Swanston St #5"
+    );
+
+    // Mixed features and collections are combined
+    let combined = geojson_collection(
+        response
+            .routes
+            .values()
+            .map(RouteDetails::to_geojson)
+            .chain(response.stops.values().map(StopDetails::to_geojson)),
+    );
+    assert_eq!(combined["features"].as_array().unwrap().len(), 3);
+    assert!(combined["features"][2].get("features").is_none());
+}
+
+#[test]
+fn test_resolved_departures() {
+    let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let mut response: DeparturesResponse = serde_json::from_str(&data).unwrap();
+
+    let departures: Vec<_> = response.resolved().collect();
+    assert_eq!(departures.len(), response.departures.len());
+
+    let first = departures[0];
+    assert_eq!(first.run_ref, RunRef::new("952051".to_string()));
+    assert_eq!(first.stop().unwrap().stop_name, "Southern Cross Station");
+    assert_eq!(first.route().unwrap().route_name, "Sunbury");
+    assert_eq!(first.run().unwrap().express_stop_count, 2);
+    assert_eq!(
+        first.direction().unwrap().direction_name,
+        "City (Flinders Street)"
+    );
+    assert_eq!(first.destination(), Some("Sunshine"));
+    assert_eq!(first.platform(), Some("14"));
+    assert_eq!(first.delay(), Some(time::Duration::minutes(1)));
+    assert_eq!(
+        first.departure_time(),
+        first.estimated_departure_utc.unwrap()
+    );
+    let disruptions: Vec<_> = first.disruptions().collect();
+    assert_eq!(disruptions.len(), 1);
+    assert_eq!(disruptions[0].disruption_type, "Planned Works");
+
+    // No real-time estimate
+    let third = departures[2];
+    assert_eq!(third.delay(), None);
+    assert_eq!(third.departure_time(), third.scheduled_departure_utc);
+
+    // Missing expansions are None rather than panics
+    response.runs.clear();
+    response.routes.clear();
+    response.disruptions.clear();
+    let first = response.resolved().next().unwrap();
+    assert!(first.run().is_none());
+    assert!(first.route().is_none());
+    assert!(first.destination().is_none());
+    assert_eq!(first.disruptions().count(), 0);
+    assert!(first.stop().is_some());
+}
+
+/// The train fixture, and a later snapshot of it with various changes
+fn changed_departures() -> (serde_json::Value, serde_json::Value) {
+    let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let original: serde_json::Value = serde_json::from_str(&data).unwrap();
+
+    let mut changed = original.clone();
+    let departures = changed["departures"].as_array_mut().unwrap();
+    departures[0]["estimated_departure_utc"] = serde_json::json!("2023-07-20T09:38:00Z");
+    departures[0]["at_platform"] = serde_json::json!(false);
+    departures[1]["platform_number"] = serde_json::json!("12");
+    departures[1]["estimated_departure_utc"] = serde_json::json!("2023-07-20T09:38:30Z");
+    departures[2]["disruption_ids"] = serde_json::json!([]);
+    departures[3]["disruption_ids"] = serde_json::json!([287344]);
+    // Everything in the fixture is in the past, so give the new departure a future time
+    let mut new_departure = departures.remove(4);
+    new_departure["run_ref"] = serde_json::json!("958224");
+    new_departure["scheduled_departure_utc"] = serde_json::json!("2999-01-01T00:00:00Z");
+    new_departure["estimated_departure_utc"] = serde_json::Value::Null;
+    departures.push(new_departure);
+    changed["runs"]["951845"]["status"] = serde_json::json!("cancelled");
+
+    (original, changed)
+}
+
+/// Short description of a departure change, for comparing in tests
+fn describe_change(change: &DepartureChange) -> String {
+    let run_ref = &change.departure().run_ref;
+    match change {
+        DepartureChange::Added(_) => format!("Added {run_ref}"),
+        DepartureChange::Vanished(_) => format!("Vanished {run_ref}"),
+        DepartureChange::EstimateChanged {
+            previous, current, ..
+        } => {
+            let time = |t: &Option<OffsetDateTime>| t.map(|t| t.time().to_string());
+            format!(
+                "EstimateChanged {run_ref} {:?} -> {:?}",
+                time(previous),
+                time(current)
+            )
+        }
+        DepartureChange::DelayChanged {
+            previous, current, ..
+        } => format!("DelayChanged {run_ref} {previous:?} -> {current:?}"),
+        DepartureChange::PlatformChanged {
+            previous, current, ..
+        } => format!("PlatformChanged {run_ref} {previous:?} -> {current:?}"),
+        DepartureChange::AtPlatformChanged { at_platform, .. } => {
+            format!("AtPlatformChanged {run_ref} {at_platform}")
+        }
+        DepartureChange::Cancelled(_) => format!("Cancelled {run_ref}"),
+        DepartureChange::DisruptionAdded { disruption_id, .. } => {
+            format!("DisruptionAdded {run_ref} {disruption_id}")
+        }
+        DepartureChange::DisruptionRemoved { disruption_id, .. } => {
+            format!("DisruptionRemoved {run_ref} {disruption_id}")
+        }
+    }
+}
+
+#[test]
+fn test_diff() {
+    let (original, changed) = changed_departures();
+    let original: DeparturesResponse = serde_json::from_value(original).unwrap();
+    let changed: DeparturesResponse = serde_json::from_value(changed).unwrap();
+
+    let summary: Vec<_> = diff(&original, &changed)
+        .iter()
+        .map(describe_change)
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            // An estimate changing by less than a minute isn't a delay change
+            "EstimateChanged 951843 Some(\"9:38:00.0\") -> Some(\"9:38:30.0\")",
+            "PlatformChanged 951843 Some(\"13\") -> Some(\"12\")",
+            "Cancelled 951845",
+            "EstimateChanged 952051 Some(\"9:36:00.0\") -> Some(\"9:38:00.0\")",
+            "DelayChanged 952051 Some(1) -> Some(3)",
+            "AtPlatformChanged 952051 false",
+            "DisruptionRemoved 952053 287344",
+            "DisruptionAdded 958220 287344",
+            "Added 958224",
+            "Vanished 958222",
+        ]
+    );
+
+    // Deterministic, and symmetric for additions/removals
+    let again: Vec<_> = diff(&original, &changed)
+        .iter()
+        .map(describe_change)
+        .collect();
+    assert_eq!(summary, again);
+    let reverse: Vec<_> = diff(&changed, &original)
+        .iter()
+        .map(describe_change)
+        .collect();
+    assert!(reverse.contains(&"Vanished 958224".to_string()));
+    assert!(reverse.contains(&"Added 958222".to_string()));
+    assert!(!reverse.iter().any(|change| change.starts_with("Cancelled")));
+
+    assert!(diff(&original, &original).is_empty());
+
+    // Departures at different stops of the same run are distinct
+    let mut other_stop = original.clone();
+    other_stop.departures[0].stop_id = StopId::new(1071);
+    let summary: Vec<_> = diff(&original, &other_stop)
+        .iter()
+        .map(describe_change)
+        .collect();
+    assert_eq!(summary, ["Added 952051", "Vanished 952051"]);
+}
+
+/// Test the changes reported between polls of departures
+#[cfg(feature = "watch")]
+#[tokio::test]
+async fn test_watch_departures() {
+    use futures_util::StreamExt;
+
+    let (original, mut changed) = changed_departures();
+    let first = original.to_string();
+    let second = changed.to_string();
+    // Only the departure in the future hasn't left yet
+    changed["departures"] = serde_json::json!([]);
+    let third = changed.to_string();
+
+    let client = SequenceClient::new([
+        Ok(first),
+        Err(Error::Other("Temporary failure".to_string())),
+        Ok(second),
+        Ok(third),
+    ]);
+    let ptv = PTV::with_mock_client(client);
+    let stream = ptv.watch_departures(
+        &SOUTHERN_CROSS,
+        Default::default(),
+        std::time::Duration::from_millis(1),
+    );
+    let updates: Vec<_> = stream.take(4).collect().await;
+
+    let update = updates[0].as_ref().unwrap();
+    assert_eq!(update.snapshot.departures.len(), 6);
+    assert!(update.events.is_empty());
+
+    assert!(matches!(updates[1], Err(Error::Other(_))));
+
+    // Changes are relative to the last successful poll
+    let describe = |event: &DepartureEvent| match event {
+        DepartureEvent::Departed(departure) => format!("Departed {}", departure.run_ref),
+        DepartureEvent::Changed(change) => describe_change(change),
+    };
+    let events = &updates[2].as_ref().unwrap().events;
+    let summary: Vec<_> = events.iter().map(describe).collect();
+    assert_eq!(summary.len(), 10);
+    // Every estimate change is reported, even within the same minute of delay
+    assert_eq!(
+        summary[0],
+        "EstimateChanged 951843 Some(\"9:38:00.0\") -> Some(\"9:38:30.0\")"
+    );
+    assert_eq!(summary[8], "Added 958224");
+    assert_eq!(summary[9], "Departed 958222");
+
+    let events = &updates[3].as_ref().unwrap().events;
+    let summary: Vec<_> = events.iter().map(describe).collect();
+    assert_eq!(
+        summary,
+        [
+            "Departed 951843",
+            "Departed 951845",
+            "Departed 952051",
+            "Departed 952053",
+            "Departed 958220",
+            "Vanished 958224",
+        ]
+    );
+}
+
+/// Mock responses for the journey planner fixtures, keyed by the requests made
+/// when planning from `departure_time`
+fn planner_mock(departure_time: OffsetDateTime) -> HashMap<String, Result<String>> {
+    let date = departure_time
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap()
+        .replace(':', "%3A");
+    let mut mapping = HashMap::new();
+    for stop in [1162, 1026] {
+        let data = std::fs::read_to_string(format!("testdata/planner/departures_0_{stop}.json"));
+        let key = format!(
+            "/v3/departures/route_type/0/stop/{stop}?date_utc={date}&max_results=5&expand=Run&expand=Route"
+        );
+        mapping.insert(key, Ok(data.unwrap()));
+    }
+    for run in 950001..=950005 {
+        let data = std::fs::read_to_string(format!("testdata/planner/pattern_0_{run}.json"));
+        let key =
+            format!("/v3/pattern/run/{run}/route_type/0?expand=Run&expand=Route&date_utc={date}");
+        mapping.insert(key, Ok(data.unwrap()));
+    }
+    mapping
+}
+
+#[tokio::test]
+async fn test_plan_journey() {
+    let departure_time = time::macros::datetime!(2023-07-20 09:00 UTC);
+    let ptv = PTV::mock(planner_mock(departure_time));
+    let richmond = Stop::from_str("Stop:Train/1162").unwrap();
+    let box_hill = Stop::from_str("Stop:Train/1026").unwrap();
+
+    let itineraries = ptv
+        .plan_journey(&richmond, &box_hill, departure_time, Default::default())
+        .await
+        .unwrap();
+    let summary: Vec<_> = itineraries
+        .iter()
+        .map(|itinerary| {
+            itinerary
+                .legs()
+                .iter()
+                .map(|leg| {
+                    format!(
+                        "{} {}->{}",
+                        leg.run.id, leg.board.stop_id, leg.alight.stop_id
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+    // Changing from 950001 to 950003 at Camberwell is worse than staying on 950001
+    assert_eq!(
+        summary,
+        [
+            "950001 1162->1026",
+            "950002 1162->1032, 950003 1032->1026",
+            "950005 1162->1026",
+        ]
+    );
+
+    // Real-time estimates are used where available
+    let direct = &itineraries[0];
+    assert_eq!(direct.transfers(), 0);
+    assert_eq!(
+        direct.departure_time(),
+        time::macros::datetime!(2023-07-20 09:05 UTC)
+    );
+    assert_eq!(
+        direct.arrival_time(),
+        time::macros::datetime!(2023-07-20 09:25 UTC)
+    );
+    assert_eq!(direct.duration(), time::Duration::minutes(20));
+    let leg = &direct.legs()[0];
+    assert_eq!(leg.route_name.as_deref(), Some("Lilydale"));
+    assert_eq!(leg.destination_name.as_deref(), Some("Lilydale"));
+    assert_eq!(leg.board.platform_number.as_deref(), Some("3"));
+    assert_eq!(leg.from(), richmond);
+    assert_eq!(leg.to(), box_hill);
+
+    let transfer = &itineraries[1];
+    assert_eq!(transfer.transfers(), 1);
+    assert_eq!(
+        transfer.legs()[0].alight.platform_number.as_deref(),
+        Some("1")
+    );
+    assert_eq!(transfer.legs()[1].route_name.as_deref(), Some("Belgrave"));
+    assert_eq!(
+        transfer.arrival_time(),
+        time::macros::datetime!(2023-07-20 09:28 UTC)
+    );
+
+    // Not enough time to change at Camberwell
+    let params = JourneyParams {
+        min_transfer: time::Duration::minutes(5),
+        ..Default::default()
+    };
+    let itineraries = ptv
+        .plan_journey(&richmond, &box_hill, departure_time, params)
+        .await
+        .unwrap();
+    assert!(itineraries
+        .iter()
+        .all(|itinerary| itinerary.transfers() == 0));
+
+    // Leaving later skips earlier services
+    let departure_time = time::macros::datetime!(2023-07-20 09:10 UTC);
+    let ptv = PTV::mock(planner_mock(departure_time));
+    let itineraries = ptv
+        .plan_journey(&richmond, &box_hill, departure_time, Default::default())
+        .await
+        .unwrap();
+    assert_eq!(itineraries.len(), 1);
+    assert_eq!(itineraries[0].legs()[0].run.id.value(), "950005");
+
+    // A pattern that can't be fetched only drops the itineraries using that run
+    let departure_time = time::macros::datetime!(2023-07-20 09:00 UTC);
+    let mut mapping = planner_mock(departure_time);
+    mapping.insert(
+        "/v3/pattern/run/950003/route_type/0?expand=Run&expand=Route&date_utc=2023-07-20T09%3A00%3A00Z"
+            .to_string(),
+        Err(Error::HTTP(StatusCode::NOT_FOUND, "Not Found".to_string())),
+    );
+    let ptv = PTV::mock(mapping);
+    let itineraries = ptv
+        .plan_journey(&richmond, &box_hill, departure_time, Default::default())
+        .await
+        .unwrap();
+    assert_eq!(itineraries.len(), 2);
+    assert!(itineraries
+        .iter()
+        .all(|itinerary| itinerary.transfers() == 0));
+
+    // Pattern requests are limited by the concurrency
+    let client = ConcurrencyClient {
+        inner: Some(MockClient(planner_mock(departure_time))),
+        ..Default::default()
+    };
+    let max_in_flight = client.max_in_flight.clone();
+    let ptv = PTV::with_mock_client(client);
+    let params = JourneyParams {
+        concurrency: 2,
+        ..Default::default()
+    };
+    let itineraries = ptv
+        .plan_journey(&richmond, &box_hill, departure_time, params)
+        .await
+        .unwrap();
+    assert_eq!(itineraries.len(), 3);
+    assert_eq!(max_in_flight.load(std::sync::atomic::Ordering::SeqCst), 2);
+}
+
+/// Mock client which tracks the number of requests in flight
+///
+/// Responses come from `inner` if it's set, otherwise they're all the tram departures fixture.
+#[derive(Default)]
+struct ConcurrencyClient {
+    in_flight: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    max_in_flight: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    inner: Option<MockClient>,
+}
+impl PTVHttpClient for ConcurrencyClient {
+    fn api_get<T: DeserializeOwned + Send + 'static>(&self, url: Url) -> FutureResult<T> {
+        use std::sync::atomic::Ordering;
+        let in_flight = self.in_flight.clone();
+        let max_in_flight = self.max_in_flight.clone();
+        let response = self.inner.as_ref().map(|inner| inner.api_get(url));
+        Box::pin(async move {
+            let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(current, Ordering::SeqCst);
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            match response {
+                Some(response) => response.await,
+                None => {
+                    let data = std::fs::read_to_string("testdata/departures_1_2479.json").unwrap();
+                    parse_json(&data)
+                }
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_departures_many() {
+    let train = Stop::from_str("Stop:Train/1181").unwrap();
+    let vline = Stop::from_str("Stop:Vline/1181").unwrap();
+    let missing = Stop::from_str("Stop:Bus/404").unwrap();
+    let mut mapping = HashMap::new();
+    for (stop, path) in [(&train, "departures_0_1181"), (&vline, "departures_3_1181")] {
+        let data = std::fs::read_to_string(format!("testdata/{path}.json")).unwrap();
+        let key = format!(
+            "/v3/departures/route_type/{}/stop/{}",
+            stop.route_type.value(),
+            stop.id
+        );
+        mapping.insert(key, Ok(data));
+    }
+    mapping.insert(
+        "/v3/departures/route_type/2/stop/404".to_string(),
+        Err(Error::HTTP(StatusCode::NOT_FOUND, "Not Found".to_string())),
+    );
+    let ptv = PTV::mock(mapping);
+
+    // The same stop twice doesn't duplicate departures
+    let stops = [train.clone(), missing.clone(), vline, train];
+    let result = ptv.departures_many(&stops, Default::default(), 2).await;
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].0, missing);
+    assert!(matches!(
+        result.errors[0].1,
+        Error::HTTP(StatusCode::NOT_FOUND, _)
+    ));
+
+    let response = result.response.unwrap();
+    assert_eq!(response.departures.len(), 9);
+    assert!(response
+        .departures
+        .windows(2)
+        .all(|pair| pair[0].scheduled_departure_utc <= pair[1].scheduled_departure_utc));
+    assert_eq!(response.routes.len(), 6);
+    assert_eq!(response.runs.len(), 9);
+    assert_eq!(response.disruptions.len(), 2);
+    // Train and V/Line stops share an identifier, the first one wins
+    assert_eq!(response.stops.len(), 1);
+    assert_eq!(
+        response.stops[&StopId::new(1181)].route_type,
+        RouteType::TRAIN
+    );
+    // Every departure still resolves
+    assert!(response.resolved().all(|d| d.run().is_some()));
+
+    // Everything failing
+    let result = ptv.departures_many(&[missing], Default::default(), 2).await;
+    assert!(result.response.is_none());
+    assert_eq!(result.errors.len(), 1);
+
+    // Requests are limited to the concurrency
+    let client = ConcurrencyClient::default();
+    let max_in_flight = client.max_in_flight.clone();
+    let ptv = PTV::with_mock_client(client);
+    let stops: Vec<_> = (0..6)
+        .map(|id| Stop {
+            route_type: RouteType::TRAM,
+            id: StopId::new(id),
+        })
+        .collect();
+    let result = ptv.departures_many(&stops, Default::default(), 3).await;
+    assert!(result.errors.is_empty());
+    assert_eq!(result.response.unwrap().departures.len(), 5);
+    assert_eq!(max_in_flight.load(std::sync::atomic::Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_departures_near() {
+    let location = LatLon::new(-37.8136, 144.9631);
+    let mut mapping = HashMap::new();
+    let data = std::fs::read_to_string("testdata/stops_location.json").unwrap();
+    let path = "/v3/stops/location/-37.8136,144.9631?max_distance=1500.0";
+    mapping.insert(path.to_string(), Ok(data));
+    let expand = "?expand=Route&expand=Run";
+    for (path, name) in [
+        ("/v3/departures/route_type/0/stop/1181", "departures_0_1181"),
+        ("/v3/departures/route_type/1/stop/2479", "departures_1_2479"),
+    ] {
+        let data = std::fs::read_to_string(format!("testdata/{name}.json")).unwrap();
+        mapping.insert(format!("{path}{expand}"), Ok(data));
+    }
+    mapping.insert(
+        format!("/v3/departures/route_type/3/stop/1181{expand}"),
+        Err(Error::HTTP(StatusCode::NOT_FOUND, "Not Found".to_string())),
+    );
+    let ptv = PTV::mock(mapping.clone());
+
+    let params = DeparturesParams {
+        expand: Some(vec![ResponseExpand::Route, ResponseExpand::Run]),
+        ..Default::default()
+    };
+    let result = ptv
+        .departures_near(location, 1500.0, None, params, 4)
+        .await
+        .unwrap();
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(
+        result.errors[0].0,
+        Stop::from_str("Stop:Vline/1181").unwrap()
+    );
+
+    assert_eq!(result.departures.len(), 11);
+    assert!(result
+        .departures
+        .windows(2)
+        .all(|pair| pair[0].departure_time() <= pair[1].departure_time()));
+    for departure in &result.departures {
+        assert_eq!(departure.stop.stop_id, departure.departure.stop_id);
+        assert!(departure.route.is_some());
+        assert!(departure.run.is_some());
+        let expected = location.distance(&departure.stop.location());
+        assert_eq!(departure.walking_distance, expected);
+        assert!(departure.walking_distance < 1500.0);
+    }
+    let tram = result
+        .departures
+        .iter()
+        .find(|d| d.stop.route_type == RouteType::TRAM)
+        .unwrap();
+    assert!((200.0..260.0).contains(&tram.walking_distance));
+
+    // Departures requests are limited by the concurrency
+    let client = ConcurrencyClient {
+        inner: Some(MockClient(mapping.clone())),
+        ..Default::default()
+    };
+    let max_in_flight = client.max_in_flight.clone();
+    let ptv = PTV::with_mock_client(client);
+    let params = DeparturesParams {
+        expand: Some(vec![ResponseExpand::Route, ResponseExpand::Run]),
+        ..Default::default()
+    };
+    let limited = ptv
+        .departures_near(location, 1500.0, None, params, 1)
+        .await
+        .unwrap();
+    assert_eq!(limited.departures.len(), 11);
+    assert_eq!(max_in_flight.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // Train and V/Line stops share an identifier, each departure keeps the stop it came from
+    // (even without routes, which would give the route type)
+    let mut vline_runs = Vec::new();
+    for (path, name) in [
+        ("/v3/departures/route_type/0/stop/1181", "departures_0_1181"),
+        ("/v3/departures/route_type/3/stop/1181", "departures_3_1181"),
+        ("/v3/departures/route_type/1/stop/2479", "departures_1_2479"),
+    ] {
+        let data = std::fs::read_to_string(format!("testdata/{name}.json")).unwrap();
+        let mut json: serde_json::Value = serde_json::from_str(&data).unwrap();
+        json["routes"] = serde_json::json!({});
+        if name == "departures_3_1181" {
+            let response: DeparturesResponse = serde_json::from_value(json.clone()).unwrap();
+            vline_runs.extend(response.departures.into_iter().map(|d| d.run_ref));
+        }
+        mapping.insert(path.to_string(), Ok(json.to_string()));
+    }
+    let ptv = PTV::mock(mapping);
+    let result = ptv
+        .departures_near(location, 1500.0, None, Default::default(), 4)
+        .await
+        .unwrap();
+    assert!(result.errors.is_empty());
+    assert_eq!(result.departures.len(), 11 + vline_runs.len());
+    for departure in &result.departures {
+        let vline = vline_runs.contains(&departure.departure.run_ref);
+        assert_eq!(departure.stop.route_type == RouteType::VLINE, vline);
+        let expected = location.distance(&departure.stop.location());
+        assert_eq!(departure.walking_distance, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_resolve_stop() {
+    let data = std::fs::read_to_string("testdata/search_stops.json").unwrap();
+    let stops = parse_json::<SearchResponse>(&data).unwrap().stops;
+    let params = ResolveParams::default();
+    let resolve = |text: &str, params: &ResolveParams| rank_stops(text, stops.clone(), params);
+    let found = |text: &str, params: &ResolveParams| resolve(text, params).stop();
+
+    // Abbreviations, case, and a trailing suburb are ignored
+    let flinders = Stop::from_str("Stop:Train/1071").unwrap();
+    assert_eq!(
+        found("Flinders Street Station", &params),
+        Some(flinders.clone())
+    );
+    assert_eq!(found("flinders st stn", &params), Some(flinders.clone()));
+    assert_eq!(found("Flinders St, Melbourne", &params), Some(flinders));
+    let glenferrie = Stop::from_str("Stop:Train/1080").unwrap();
+    assert_eq!(found("Glenferrie Stn Hawthorn", &params), Some(glenferrie));
+    // A leading "St" is Saint, not Street
+    let st_kilda = Stop::from_str("Stop:Tram/2791").unwrap();
+    assert_eq!(found("St Kilda Road/Toorak Road", &params), Some(st_kilda));
+
+    // The same name for different modes is ambiguous, unless a mode is preferred
+    let StopResolution::Ambiguous(candidates) = resolve("Southern Cross", &params) else {
+        panic!("Southern Cross should be ambiguous");
+    };
+    let candidates: Vec<_> = candidates.iter().map(|c| c.stop.stop()).collect();
+    assert_eq!(
+        candidates,
+        [
+            Stop::from_str("Stop:Train/1181").unwrap(),
+            Stop::from_str("Stop:Vline/1181").unwrap(),
+        ]
+    );
+    let prefer_vline = ResolveParams {
+        preferred_route_types: vec![RouteType::VLINE, RouteType::TRAIN],
+        ..Default::default()
+    };
+    assert_eq!(
+        found("Southern Cross", &prefer_vline),
+        Some(Stop::from_str("Stop:Vline/1181").unwrap())
+    );
+
+    assert!(matches!(
+        resolve("Xyzzy", &params),
+        StopResolution::NotFound
+    ));
+
+    // Literals don't need the API, everything else searches
+    let mut mapping = HashMap::new();
+    mapping.insert(
+        "/v3/search/Glenferrie?include_outlets=false&match_stop_by_suburb=false&match_route_by_suburb=false"
+            .to_string(),
+        Ok(data),
+    );
+    let ptv = PTV::mock(mapping);
+    let result = ptv
+        .resolve_stop("Stop:Tram/2479", Default::default())
+        .await
+        .unwrap();
+    assert!(matches!(result, StopResolution::Literal(_)));
+    let result = ptv
+        .resolve_stop("Glenferrie (Hawthorn)", Default::default())
+        .await
+        .unwrap();
+    let StopResolution::Found(candidate) = result else {
+        panic!("Glenferrie should be found");
+    };
+    assert_eq!(candidate.stop.stop_name, "Glenferrie Station");
+}
+
+#[tokio::test]
+async fn test_network_index() {
+    let mut mapping = HashMap::new();
+    for (path, name) in [
+        ("/v3/route_types", "route_types"),
+        ("/v3/routes?route_types=0&route_types=1", "routes"),
+        ("/v3/directions/route/1", "directions_1"),
+        ("/v3/directions/route/1881", "directions_1881"),
+        ("/v3/stops/route/1/route_type/0", "stops_route_0_1"),
+        ("/v3/stops/route/1881/route_type/1", "stops_route_1_1881"),
+    ] {
+        let data = std::fs::read_to_string(format!("testdata/index/{name}.json")).unwrap();
+        mapping.insert(path.to_string(), Ok(data));
+    }
+    let ptv = PTV::mock(mapping);
+    let index = ptv.build_index().await.unwrap();
+    assert_eq!(index.version, INDEX_VERSION);
+    assert_eq!(index.route_types.len(), 2);
+    assert_eq!(index.routes.len(), 2);
+    assert_eq!(index.stops.len(), 7);
+
+    // Written and read back, it still answers the same
+    let mut file = Vec::new();
+    index.write(&mut file).unwrap();
+    let index = NetworkIndex::read(file.as_slice()).unwrap();
+
+    let alamein = index
+        .route(&Route::from_str("Route:Train/1").unwrap())
+        .unwrap();
+    assert_eq!(alamein.route.route_name, "Alamein");
+    assert_eq!(alamein.directions.len(), 2);
+    assert_eq!(alamein.stops.len(), 4);
+    assert!(index
+        .route(&Route::from_str("Route:Tram/1").unwrap())
+        .is_none());
+
+    let richmond = index
+        .stop(&Stop::from_str("Stop:Train/1162").unwrap())
+        .unwrap();
+    assert_eq!(richmond.stop.stop_name, "Richmond Station");
+    assert_eq!(richmond.routes, [RouteId::new(1)]);
+    assert!(index
+        .stop(&Stop::from_str("Stop:Tram/1162").unwrap())
+        .is_none());
+
+    let params = ResolveParams::default();
+    let found = index.search_stops("camberwell stn", &params).stop();
+    assert_eq!(found, Some(Stop::from_str("Stop:Train/1032").unwrap()));
+    let routes = index.search_routes("1");
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].route.route_id, RouteId::new(1881));
+    assert_eq!(index.search_routes("coburg").len(), 1);
+
+    // Flinders Street Station, then the tram stop across the road
+    let nearest = index.nearest_stops(LatLon::new(-37.8183, 144.9671), 200.0, 5);
+    let nearest: Vec<_> = nearest
+        .iter()
+        .map(|(s, _)| s.stop.stop_id.clone())
+        .collect();
+    assert_eq!(nearest, [StopId::new(1071), StopId::new(2170)]);
+
+    // Other versions are rejected
+    let mut json: serde_json::Value = serde_json::from_slice(&file).unwrap();
+    json["version"] = serde_json::json!(INDEX_VERSION + 1);
+    let file = serde_json::to_vec(&json).unwrap();
+    let error = NetworkIndex::read(file.as_slice()).unwrap_err();
+    assert!(error.to_string().contains("Unsupported index version"));
+}
+
+#[test]
+fn test_melbourne_time() {
+    use time::macros::datetime;
+    let offset = |utc| datetime::to_melbourne(utc).offset().whole_hours();
+    // Daylight saving starts on the first Sunday in October, and ends on the first Sunday in April
+    assert_eq!(offset(datetime!(2023-07-20 09:00 UTC)), 10);
+    assert_eq!(offset(datetime!(2023-09-30 15:59 UTC)), 10);
+    assert_eq!(offset(datetime!(2023-09-30 16:00 UTC)), 11);
+    assert_eq!(offset(datetime!(2023-12-31 23:00 UTC)), 11);
+    assert_eq!(offset(datetime!(2024-04-06 15:59 UTC)), 11);
+    assert_eq!(offset(datetime!(2024-04-06 16:00 UTC)), 10);
+    assert_eq!(
+        datetime::melbourne_midnight(time::macros::date!(2024 - 01 - 01)),
+        datetime!(2023-12-31 13:00 UTC)
+    );
+}
+
+#[tokio::test]
+async fn test_gtfs_feed() {
+    let mut mapping = HashMap::new();
+    for key in ["/v3/routes?route_types=0", "/v3/routes"] {
+        let data = std::fs::read_to_string("testdata/gtfs/routes.json").unwrap();
+        mapping.insert(key.to_string(), Ok(data));
+    }
+    // Each Melbourne day starts at 14:00 UTC the day before
+    let days = ["2023-07-18", "2023-07-19", "2023-07-20"]
+        .map(|day| format!("date_utc={day}T14%3A00%3A00Z"));
+    let runs_key = |route: u32, day: &str| format!("/v3/runs/route/{route}/route_type/0?{day}");
+    let pattern_key = |run: u32, day: &str| {
+        format!("/v3/pattern/run/{run}/route_type/0?expand=Stop&expand=Route&expand=Run&{day}")
+    };
+    for day in &days {
+        for route in [9, 1] {
+            let data = std::fs::read_to_string(format!("testdata/gtfs/runs_0_{route}.json"));
+            mapping.insert(runs_key(route, day), Ok(data.unwrap()));
+        }
+        for run in [950001, 950002, 950005] {
+            let data = std::fs::read_to_string(format!("testdata/planner/pattern_0_{run}.json"));
+            mapping.insert(pattern_key(run, day), Ok(data.unwrap()));
+        }
+    }
+    let ptv = PTV::mock(mapping.clone());
+
+    let date = time::macros::date!(2023 - 07 - 20);
+    let params = GtfsParams {
+        start: date.previous_day().unwrap(),
+        end: date,
+        route_types: Some(vec![RouteType::TRAIN]),
+    };
+    let response = ptv.gtfs_feed(params).await.unwrap();
+    assert!(response.errors.is_empty());
+    let feed = response.feed;
+    // The same runs are returned for both days, but only run on the second
+    assert_eq!(feed.trip_count(), 3);
+
+    let files: BTreeMap<_, _> = feed.files().into_iter().collect();
+    let names: Vec<_> = files.keys().copied().collect();
+    assert_eq!(
+        names,
+        [
+            "agency.txt",
+            "calendar_dates.txt",
+            "routes.txt",
+            "stop_times.txt",
+            "stops.txt",
+            "trips.txt"
+        ]
+    );
+    // The route_gtfs_id is kept, otherwise one is made up
+    assert_eq!(
+        files["routes.txt"],
+        "route_id,agency_id,route_short_name,route_long_name,route_type\r\n\
+         ptv-0-1,PTV,,\"Alamein, via Burnley\",2\r\n\
+         2-LIL,PTV,,Lilydale,2\r\n"
+    );
+    assert_eq!(
+        files["calendar_dates.txt"],
+        "service_id,date,exception_type\r\n20230720,20230720,1\r\n"
+    );
+    assert_eq!(files["trips.txt"].lines().count(), 4);
+    assert!(files["trips.txt"].contains("2-LIL,20230720,0-950001-20230720,Lilydale\r\n"));
+    assert_eq!(files["stops.txt"].lines().count(), 7);
+    assert!(files["stops.txt"].contains("0-1071,Flinders Street Station,-37.8183,144.966965\r\n"));
+    // Melbourne local time
+    let stop_times = &files["stop_times.txt"];
+    assert_eq!(stop_times.lines().count(), 13);
+    assert!(stop_times.contains("0-950001-20230720,19:00:00,19:00:00,0-1071,1\r\n"));
+
+    #[cfg(feature = "gtfs")]
+    {
+        use std::io::Read;
+        let mut zip = std::io::Cursor::new(Vec::new());
+        feed.write_zip(&mut zip).unwrap();
+        let mut archive = zip::ZipArchive::new(zip).unwrap();
+        assert_eq!(archive.len(), 6);
+        let mut routes = String::new();
+        archive
+            .by_name("routes.txt")
+            .unwrap()
+            .read_to_string(&mut routes)
+            .unwrap();
+        assert_eq!(routes, files["routes.txt"]);
+    }
+
+    // Nothing runs the day after
+    let params = GtfsParams {
+        start: date.next_day().unwrap(),
+        end: date.next_day().unwrap(),
+        route_types: None,
+    };
+    let feed = ptv.gtfs_feed(params).await.unwrap().feed;
+    assert_eq!(feed.trip_count(), 0);
+
+    // Failed runs and patterns are left out and reported, rather than failing the feed
+    let forbidden = || Err(Error::HTTP(StatusCode::FORBIDDEN, "Forbidden".to_string()));
+    for day in &days[..2] {
+        mapping.insert(runs_key(1, day), forbidden());
+        mapping.insert(pattern_key(950005, day), forbidden());
+    }
+    let params = GtfsParams {
+        start: date.previous_day().unwrap(),
+        end: date,
+        route_types: Some(vec![RouteType::TRAIN]),
+    };
+    let response = PTV::mock(mapping).gtfs_feed(params).await.unwrap();
+    assert_eq!(response.feed.trip_count(), 1);
+    let failed: Vec<_> = response.errors.iter().map(|(request, _)| request).collect();
+    let route = Route::from_str("Route:Train/1").unwrap();
+    let run = Run::from_str("Run:Train/950005").unwrap();
+    let yesterday = date.previous_day().unwrap();
+    assert_eq!(
+        failed,
+        [
+            &GtfsRequest::Runs(route.clone(), yesterday),
+            &GtfsRequest::Pattern(run.clone(), yesterday),
+            &GtfsRequest::Runs(route, date),
+            &GtfsRequest::Pattern(run, date),
+        ]
+    );
+    assert!(matches!(
+        response.errors[0].1,
+        Error::HTTP(StatusCode::FORBIDDEN, _)
+    ));
+}
+
+#[cfg(feature = "gtfs-realtime")]
+#[test]
+fn test_gtfs_realtime() {
+    use gtfs_realtime::*;
+    use prost::Message;
+    use time::macros::datetime;
+    let now = datetime!(2023-07-20 09:30 UTC);
+
+    let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let train: DeparturesResponse = parse_json(&data).unwrap();
+    let message = trip_updates(&train, now);
+    assert_eq!(message.header.gtfs_realtime_version, GTFS_REALTIME_VERSION);
+    assert_eq!(message.header.timestamp, Some(now.unix_timestamp() as u64));
+    // Runs without real-time estimates are left out
+    assert_eq!(message.entity.len(), 4);
+    let update = message.entity[0].trip_update.as_ref().unwrap();
+    assert_eq!(update.trip.trip_id.as_deref(), Some("0-951843-20230720"));
+    assert_eq!(update.trip.start_date.as_deref(), Some("20230720"));
+    let late = message
+        .entity
+        .iter()
+        .find(|e| e.id == "trip-0-958220-20230720")
+        .unwrap();
+    let update = &late.trip_update.as_ref().unwrap().stop_time_update[0];
+    assert_eq!(update.stop_id.as_deref(), Some("0-1181"));
+    assert_eq!(update.stop_sequence, None);
+    assert_eq!(update.departure.as_ref().unwrap().delay, Some(180));
+
+    // Binary round trips, JSON uses the protobuf field names
+    let decoded = FeedMessage::decode(message.to_bytes().as_slice()).unwrap();
+    assert_eq!(decoded, message);
+    let json: serde_json::Value = serde_json::from_str(&message.to_json()).unwrap();
+    assert_eq!(json["header"]["gtfs_realtime_version"], "2.0");
+    assert_eq!(
+        json["entity"][0]["trip_update"]["trip"]["route_id"],
+        "2-CGB"
+    );
+
+    let message = alerts(train.disruptions.values(), now);
+    assert_eq!(message.entity.len(), 1);
+    let alert = message.entity[0].alert.as_ref().unwrap();
+    assert_eq!(message.entity[0].id, "alert-287344");
+    assert_eq!(alert.cause, Some(Cause::Maintenance as i32));
+    let informed: Vec<_> = alert
+        .informed_entity
+        .iter()
+        .map(|e| e.route_id.clone().or(e.stop_id.clone()).unwrap())
+        .collect();
+    assert_eq!(informed, ["2-SUY", "0-1218", "0-1187"]);
+    assert_eq!(
+        alert.active_period[0].end,
+        Some(datetime!(2023-07-20 17:00 UTC).unix_timestamp() as u64)
+    );
+
+    let data = std::fs::read_to_string("testdata/departures_2_17805.json").unwrap();
+    let bus: DeparturesResponse = parse_json(&data).unwrap();
+    let message = vehicle_positions(&bus, now);
+    assert_eq!(message.entity.len(), 1);
+    let vehicle = message.entity[0].vehicle.as_ref().unwrap();
+    let location = bus.runs[&RunRef::new("5-903-mjp-1.10.R-3-21".to_string())]
+        .vehicle_position
+        .as_ref()
+        .unwrap()
+        .location()
+        .unwrap();
+    let position = vehicle.position.as_ref().unwrap();
+    assert_eq!(position.latitude, location.latitude as f32);
+    assert_eq!(
+        vehicle.trip.as_ref().unwrap().route_id.as_deref(),
+        Some("4-903")
+    );
+}
+
+#[cfg(all(feature = "gtfs", feature = "gtfs-realtime"))]
+#[test]
+fn test_gtfs_realtime_matches_feed() {
+    use time::macros::{date, datetime};
+    let now = datetime!(2023-07-20 09:30 UTC);
+
+    // Sequences aren't always consecutive, both feeds use them as they are
+    let data = std::fs::read_to_string("testdata/planner/pattern_0_950001.json").unwrap();
+    let mut json: serde_json::Value = serde_json::from_str(&data).unwrap();
+    for departure in json["departures"].as_array_mut().unwrap() {
+        let sequence = departure["departure_sequence"].as_i64().unwrap();
+        departure["departure_sequence"] = serde_json::json!(sequence * 100);
+    }
+    let pattern: PatternResponse = serde_json::from_value(json.clone()).unwrap();
+    let run = Run {
+        route_type: RouteType::TRAIN,
+        id: RunRef::new("950001".to_string()),
+    };
+    let mut feed = GtfsFeed::new(date!(2023 - 07 - 20), date!(2023 - 07 - 20));
+    assert!(feed.add_pattern(&run, &pattern));
+    let files: BTreeMap<_, _> = feed.files().into_iter().collect();
+    let static_sequences: Vec<String> = files["stop_times.txt"]
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let fields: Vec<_> = line.split(',').collect();
+            format!("{},{}", fields[3], fields[4])
+        })
+        .collect();
+
+    json["disruptions"] = serde_json::json!({});
+    let departures: DeparturesResponse = serde_json::from_value(json).unwrap();
+    let message = gtfs_realtime::trip_updates(&departures, now);
+    let update = message.entity[0].trip_update.as_ref().unwrap();
+    assert_eq!(update.trip.trip_id.as_deref(), Some("0-950001-20230720"));
+    let realtime_sequences: Vec<String> = update
+        .stop_time_update
+        .iter()
+        .map(|u| {
+            format!(
+                "{},{}",
+                u.stop_id.as_ref().unwrap(),
+                u.stop_sequence.unwrap()
+            )
+        })
+        .collect();
+    assert_eq!(static_sequences[0], "0-1071,100");
+    assert_eq!(realtime_sequences, static_sequences);
+}
+
+#[cfg(feature = "gtfs-realtime")]
+#[test]
+fn test_gtfs_realtime_stop_only_alert() {
+    let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let train: DeparturesResponse = parse_json(&data).unwrap();
+    let mut disruption = train.disruptions.values().next().unwrap().clone();
+    disruption.routes = Some(Vec::new());
+    assert!(!disruption.stops.as_deref().unwrap_or_default().is_empty());
+
+    // Stops can't be identified without a route type, and alerts need an informed entity
+    let message = gtfs_realtime::alerts([&disruption], OffsetDateTime::now_utc());
+    assert!(message.entity.is_empty());
+}
+
+#[tokio::test]
+async fn test_gtfs_stop_id() {
+    let gtfs_stop_id = GtfsStopId::new("vic:rail:SSS".to_string());
+    let departures_url = PTV::mock(Default::default())
+        .build_url(
+            "/v3/departures/route_type/0/stop/vic%3Arail%3ASSS",
+            DeparturesParams {
+                gtfs: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let search_url = PTV::mock(Default::default())
+        .build_url(
+            "/v3/search/vic%253Arail%253ASSS",
+            SearchParams {
+                match_stop_by_gtfs_stop_id: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let departures = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let search = std::fs::read_to_string("testdata/search_stops.json").unwrap();
+    let mut mapping = HashMap::new();
+    mapping.insert(departures_url.to_string(), Ok(departures));
+    mapping.insert(search_url.to_string(), Ok(search.clone()));
+    let ptv = PTV::mock(mapping);
+
+    // The flags are set automatically
+    let response = ptv
+        .departures_gtfs(RouteType::TRAIN, &gtfs_stop_id, Default::default())
+        .await
+        .unwrap();
+    let mut map = GtfsStopMap::default();
+    let southern_cross = Stop::from_str("Stop:Train/1181").unwrap();
+    assert_eq!(
+        map.add_departures(RouteType::TRAIN, &gtfs_stop_id, &response),
+        Some(southern_cross.clone())
+    );
+    assert_eq!(
+        map.stop(RouteType::TRAIN, &gtfs_stop_id),
+        Some(&southern_cross)
+    );
+    assert_eq!(map.stop(RouteType::VLINE, &gtfs_stop_id), None);
+    assert_eq!(map.gtfs_stop_id(&southern_cross), Some(&gtfs_stop_id));
+
+    // Searches only map when there's a single match
+    let response = ptv
+        .search_gtfs_stop(&gtfs_stop_id, Default::default())
+        .await
+        .unwrap();
+    assert_eq!(map.add_search(&gtfs_stop_id, &response), None);
+    let mut response: SearchResponse = parse_json(&search).unwrap();
+    response.stops.truncate(1);
+    response.stops[0].route_type = RouteType::VLINE;
+    assert!(map.add_search(&gtfs_stop_id, &response).is_some());
+    assert_eq!(map.len(), 2);
+    assert_eq!(
+        map.stop(RouteType::VLINE, &gtfs_stop_id),
+        Some(&Stop::from_str("Stop:Vline/1181").unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_disruption_watcher() {
+    let data = std::fs::read_to_string("testdata/disruptions.json").unwrap();
+    let mut mapping = HashMap::new();
+    mapping.insert(
+        "/v3/disruptions?route_types=0&route_types=3".to_string(),
+        Ok(data.clone()),
+    );
+    mapping.insert(
+        "/v3/disruptions/stop/1181".to_string(),
+        Err(Error::HTTP(StatusCode::NOT_FOUND, "Not Found".to_string())),
+    );
+    let ptv = PTV::mock(mapping);
+    let targets = vec![DisruptionTarget::RouteTypes(vec![
+        RouteType::TRAIN,
+        RouteType::VLINE,
+    ])];
+    let mut watcher = DisruptionWatcher::new(targets.clone(), Default::default());
+
+    // Everything is new the first time, then nothing changes
+    let events = watcher.poll(&ptv).await.unwrap();
+    let new: Vec<_> = events
+        .iter()
+        .map(|event| match event {
+            DisruptionEvent::New(disruption) => disruption.disruption_id.clone(),
+            other => panic!("Unexpected {other:?}"),
+        })
+        .collect();
+    assert_eq!(new, [DisruptionId::new(287344), DisruptionId::new(287901)]);
+    assert!(watcher.poll(&ptv).await.unwrap().is_empty());
+
+    // Saved and restored, nothing is reported again
+    let mut state = Vec::new();
+    watcher.write_state(&mut state).unwrap();
+    let mut restored = DisruptionWatcher::new(targets, Default::default());
+    restored.read_state(state.as_slice()).unwrap();
+    assert_eq!(restored.seen().len(), 2);
+    assert!(restored.poll(&ptv).await.unwrap().is_empty());
+
+    // One is updated, one resolved
+    let response: DisruptionsResponse = parse_json(&data).unwrap();
+    let mut updated = response.disruptions.metro_train[0].clone();
+    updated.title = "Buses replace trains".to_string();
+    updated.last_updated = Some("2023-07-19T01:00:00Z".to_string());
+    let events = restored.update([updated]);
+    assert_eq!(events.len(), 2);
+    let DisruptionEvent::Updated { changes, .. } = &events[0] else {
+        panic!("Expected an update, got {:?}", events[0]);
+    };
+    let fields: Vec<_> = changes.iter().map(|c| c.field.as_str()).collect();
+    assert_eq!(fields, ["last_updated", "title"]);
+    assert_eq!(changes[1].current, "Buses replace trains");
+    assert!(
+        matches!(&events[1], DisruptionEvent::Resolved(d) if d.disruption_id == DisruptionId::new(287901))
+    );
+
+    // A failed request doesn't resolve everything
+    let mut watcher = DisruptionWatcher::new(
+        vec![DisruptionTarget::Stop(StopId::new(1181))],
+        Default::default(),
+    );
+    watcher.update(response.disruptions.iter().cloned());
+    assert!(watcher.poll(&ptv).await.is_err());
+    assert_eq!(watcher.seen().len(), 2);
+}
+
+#[test]
+fn test_ical() {
+    let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let departures: DeparturesResponse = parse_json(&data).unwrap();
+    let data = std::fs::read_to_string("testdata/disruptions.json").unwrap();
+    let disruptions: DisruptionsResponse = parse_json(&data).unwrap();
+
+    let timestamp = OffsetDateTime::from_unix_timestamp(1689800000).unwrap();
+    let mut calendar = Calendar::new(Some("Trains, from Southern Cross".to_string()), timestamp);
+    calendar.add_departures(&departures);
+    assert_eq!(calendar.events.len(), 6);
+    // Only disruptions with a start date are added
+    assert_eq!(calendar.add_disruptions(disruptions.disruptions.iter()), 2);
+
+    let ics = calendar.to_string();
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert!(ics.contains("X-WR-CALNAME:Trains\\, from Southern Cross\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 8);
+    assert_eq!(ics.matches("DTSTAMP:20230719T205320Z\r\n").count(), 8);
+
+    // Departures are located at the stop
+    let first = &calendar.events[0];
+    assert_eq!(
+        first.uid,
+        "departure-952051-1181-0@timetableapi.ptv.vic.gov.au"
+    );
+    assert_eq!(first.summary, "Sunbury to Sunshine");
+    assert_eq!(first.location.as_deref(), Some("Southern Cross Station"));
+    assert!(ics.contains(
+        "UID:departure-952051-1181-0@timetableapi.ptv.vic.gov.au\r\n\
+         DTSTAMP:20230719T205320Z\r\n\
+         DTSTART:20230720T093600Z\r\n\
+         SUMMARY:Sunbury to Sunshine\r\n\
+         DESCRIPTION:Platform 14\\nDelayed 1 min\r\n\
+         LOCATION:Southern Cross Station\r\n\
+         GEO:-37.818306;144.952423\r\n"
+    ));
+
+    // Early departures aren't delayed, and less than a minute either way is on time
+    let mut early = departures.clone();
+    for (departure, offset) in early.departures.iter_mut().zip([-120, 30]) {
+        let estimated = departure.scheduled_departure_utc + time::Duration::seconds(offset);
+        departure.estimated_departure_utc = Some(estimated);
+    }
+    let mut calendar = Calendar::new(None, timestamp);
+    calendar.add_departures(&early);
+    let description = |departure: &DepartureDetails| {
+        let uid = format!("departure-{}-", departure.run_ref);
+        let event = calendar.events.iter().find(|e| e.uid.starts_with(&uid));
+        event.unwrap().description.clone().unwrap_or_default()
+    };
+    assert!(description(&early.departures[0]).ends_with("\nEarly 2 min"));
+    let on_time = description(&early.departures[1]);
+    assert!(!on_time.contains("min"), "{on_time}");
+
+    // Disruptions have their dates and link
+    assert!(ics.contains(
+        "UID:disruption-287344@timetableapi.ptv.vic.gov.au\r\n\
+         DTSTAMP:20230719T205320Z\r\n\
+         DTSTART:20230717T103000Z\r\n\
+         DTEND:20230720T170000Z\r\n"
+    ));
+    assert!(ics.contains("LOCATION:Sunshine Station\\, Sunbury Station\r\n"));
+    assert!(!ics.contains("DTSTART:20230721T200000Z\r\nDTEND"));
+
+    // Long lines are folded at 75 octets, without splitting characters
+    for line in ics.split("\r\n") {
+        assert!(line.len() <= 75, "{line}");
+    }
+    let unfolded = ics.replace("\r\n ", "");
+    assert!(unfolded.contains(&format!(
+        "URL:{}\r\n",
+        disruptions.disruptions.metro_train[0].url
+    )));
+
+    let mut calendar = Calendar::new(None, timestamp);
+    calendar.events.push(CalendarEvent {
+        summary: "é".repeat(100),
+        ..calendar_event_template(timestamp)
+    });
+    let ics = calendar.to_string();
+    for line in ics.split("\r\n") {
+        assert!(line.len() <= 75, "{line}");
+    }
+    assert!(ics
+        .replace("\r\n ", "")
+        .contains(&format!("SUMMARY:{}\r\n", "é".repeat(100))));
+}
+
+fn calendar_event_template(start: OffsetDateTime) -> CalendarEvent {
+    CalendarEvent {
+        uid: "test@example.com".to_string(),
+        start,
+        end: None,
+        summary: String::new(),
+        description: None,
+        location: None,
+        geo: None,
+        url: None,
+    }
+}
+
+#[tokio::test]
+async fn test_run() {
+    let data = std::fs::read_to_string("testdata/run_0_952051.json").unwrap();
+    let ptv = PTV::mock(HashMap::from([(
+        "/v3/runs/952051/route_type/0".to_string(),
+        Ok(data),
+    )]))
+    .with_strict(true);
+    let run: Run = "Run:Train/952051".parse().unwrap();
+    let response = ptv.run(&run, Default::default()).await.unwrap();
+    assert_eq!(response.run.run_ref, run.id);
+    assert_eq!(response.run.destination_name, "Sunshine");
+}
+
+/// Mock client which records the URLs requested, returning the same response for all of them
+struct UrlLogClient {
+    urls: std::sync::Arc<std::sync::Mutex<Vec<Url>>>,
+    response: Result<String>,
+}
+impl PTVHttpClient for UrlLogClient {
+    fn api_get<T: DeserializeOwned + Send + 'static>(&self, url: Url) -> FutureResult<T> {
+        self.urls.lock().unwrap().push(url);
+        let result = self.response.clone().and_then(|json| parse_json(&json));
+        Box::pin(ready(result))
+    }
+}
+
+#[cfg(feature = "proxy")]
+#[tokio::test]
+async fn test_signing_proxy() {
+    use std::time::Duration;
+    let data = std::fs::read_to_string("testdata/index/route_types.json").unwrap();
+    let urls = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let client = UrlLogClient {
+        urls: urls.clone(),
+        response: Ok(data),
+    };
+    let params = ProxyParams {
+        cache_ttl: Some(Duration::from_secs(60)),
+        rate_limit: Some(RateLimit {
+            requests: 2,
+            per: Duration::from_secs(3600),
+        }),
+        ..Default::default()
+    };
+    let proxy = SigningProxy::new(PTV::with_mock_client(client), params);
+    let ptv = PTV::mock(Default::default());
+
+    // Client credentials are dropped, and the request signed like any other
+    let response = proxy
+        .handle("/v3/departures/route_type/0/stop/1071?max_results=5&devid=9&signature=AB")
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let params = DeparturesParams {
+        max_results: Some(5),
+        ..Default::default()
+    };
+    let expected = ptv
+        .build_url("/v3/departures/route_type/0/stop/1071", params)
+        .await
+        .unwrap();
+    assert_eq!(urls.lock().unwrap().as_slice(), [expected]);
+    let json: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+    assert_eq!(json["route_types"][0]["route_type_name"], "Train");
+
+    // Repeated requests come from the cache, regardless of the client's credentials
+    let response = proxy
+        .handle("/v3/departures/route_type/0/stop/1071?max_results=5")
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(urls.lock().unwrap().len(), 1);
+
+    // Only /v3/ paths of the upstream API are proxied
+    for path in [
+        "/v2/route_types",
+        "/v3/../admin",
+        "/",
+        "//attacker.example/v3/route_types",
+        "http://attacker.example/v3/route_types",
+    ] {
+        assert_eq!(proxy.handle(path).await.status, StatusCode::NOT_FOUND);
+    }
+    assert_eq!(urls.lock().unwrap().len(), 1);
+
+    // The rate limit applies to requests forwarded upstream
+    let response = proxy.handle("/v3/route_types").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let expected = ptv.build_url("/v3/route_types", ()).await.unwrap();
+    assert_eq!(urls.lock().unwrap()[1], expected);
+    let response = proxy.handle("/v3/routes").await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(urls.lock().unwrap().len(), 2);
+
+    // Upstream errors are passed through, and not cached
+    let client = UrlLogClient {
+        urls: urls.clone(),
+        response: Err(Error::HTTP(StatusCode::FORBIDDEN, "Forbidden".to_string())),
+    };
+    let proxy = SigningProxy::new(PTV::with_mock_client(client), Default::default());
+    for _ in 0..2 {
+        let response = proxy.handle("/v3/route_types").await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+        assert_eq!(response.body, "Forbidden");
+    }
+    assert_eq!(urls.lock().unwrap().len(), 4);
+}
+
+#[cfg(all(feature = "proxy", feature = "reqwest"))]
+#[tokio::test]
+async fn test_signing_proxy_server() {
+    let data = std::fs::read_to_string("testdata/index/route_types.json").unwrap();
+    let upstream = PTV::mock(HashMap::from([("/v3/route_types".to_string(), Ok(data))]));
+    let router = SigningProxy::new(upstream, Default::default()).router();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    // A client can use the proxy as its base URL, its own credentials are never checked
+    let base = Url::parse(&format!("http://{address}/")).unwrap();
+    let client = PTV::new("", "", "RustPTVTest/0.1").with_base_url(base.clone());
+    let response = client.route_types().await.unwrap();
+    assert_eq!(response.route_types[0].route_type, RouteType::TRAIN);
+
+    let response = ::reqwest::get(base.join("/v2/route_types").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn test_verify_signature() {
+    let key = "00000000-0000-0000-0000-000000000000";
+    let ptv = PTV::mock(Default::default());
+
+    // Everything signed by the client verifies
+    let params = DeparturesParams {
+        max_results: Some(5),
+        expand: Some(vec![ResponseExpand::Stop, ResponseExpand::Run]),
+        ..Default::default()
+    };
+    let departures = ptv
+        .build_url("/v3/departures/route_type/0/stop/1071", params)
+        .await
+        .unwrap();
+    let route_types = ptv.build_url("/v3/route_types", ()).await.unwrap();
+    assert_eq!(verify_signature(&departures, key), Ok(()));
+    assert_eq!(verify_signature(&route_types, key), Ok(()));
+
+    // Including the double encoded search path
+    let urls = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let client = UrlLogClient {
+        urls: urls.clone(),
+        response: Err(Error::Other("Offline".to_string())),
+    };
+    let search = PTV::with_mock_client(client);
+    assert!(search
+        .search("Flinders St/Elizabeth St (Melbourne)", Default::default())
+        .await
+        .is_err());
+    let url = urls.lock().unwrap().pop().unwrap();
+    assert!(url.path().contains("%2528"));
+    assert_eq!(verify_signature(&url, key), Ok(()));
+
+    // Lowercase hex is accepted
+    let mut lower = route_types.clone();
+    lower.set_query(Some(&route_types.query().unwrap().to_lowercase()));
+    assert_eq!(verify_signature(&lower, key), Ok(()));
+
+    // Failures report why
+    let wrong_key = "11111111-1111-1111-1111-111111111111";
+    assert_eq!(
+        verify_signature(&departures, wrong_key),
+        Err(SignatureError::Mismatch)
+    );
+    let tampered = Url::parse(
+        &departures
+            .as_str()
+            .replace("max_results=5", "max_results=6"),
+    )
+    .unwrap();
+    assert_eq!(
+        verify_signature(&tampered, key),
+        Err(SignatureError::Mismatch)
+    );
+    let unsigned =
+        Url::parse("https://timetableapi.ptv.vic.gov.au/v3/route_types?devid=0").unwrap();
+    assert_eq!(
+        verify_signature(&unsigned, key),
+        Err(SignatureError::Missing)
+    );
+    let twice = Url::parse(&format!("{route_types}&signature=00")).unwrap();
+    assert_eq!(
+        verify_signature(&twice, key),
+        Err(SignatureError::Duplicated)
+    );
+    let malformed =
+        Url::parse("https://timetableapi.ptv.vic.gov.au/v3/route_types?devid=0&signature=XYZ")
+            .unwrap();
+    assert_eq!(
+        verify_signature(&malformed, key),
+        Err(SignatureError::Malformed("XYZ".to_string()))
+    );
+    let anonymous = Url::parse(&route_types.as_str().replace("devid=0&", "")).unwrap();
+    assert_eq!(
+        verify_signature(&anonymous, key),
+        Err(SignatureError::MissingDevId)
+    );
+}
+
+#[tokio::test]
+async fn test_signer() {
+    let key = "00000000-0000-0000-0000-000000000000";
+
+    // Credentials never appear in debug output
+    let ptv = PTV::mock(Default::default());
+    let signer = HmacSigner::new(key);
+    assert!(!format!("{ptv:?}").contains(key));
+    assert!(!format!("{signer:?}").contains(key));
+    assert!(format!("{signer:?}").contains("<redacted>"));
+
+    // The default signer verifies its own URLs
+    let url = ptv.build_url("/v3/route_types", ()).await.unwrap();
+    assert_eq!(signer.verify(&url), Ok(()));
+
+    // Signing can be delegated, i.e. to pre-signed URLs
+    struct PreSigned(HashMap<String, String>);
+    impl Signer for PreSigned {
+        fn sign(&self, path: &str, query: &str) -> FutureResult<String> {
+            let request = format!("{path}?{query}");
+            let signature = self.0.get(&request).cloned();
+            let result =
+                signature.ok_or_else(|| Error::Other(format!("Not pre-signed: {request}")));
+            Box::pin(ready(result))
+        }
+    }
+    let signature = signer.sign("/v3/route_types", "devid=0").await.unwrap();
+    let signatures = HashMap::from([("/v3/route_types?devid=0".to_string(), signature)]);
+    let ptv = PTV::mock(Default::default()).with_signer(PreSigned(signatures));
+    assert_eq!(ptv.build_url("/v3/route_types", ()).await.unwrap(), url);
+
+    // Requests which can't be signed fail without being sent (the mock panics on unknown URLs)
+    let error = ptv.build_url("/v3/routes", ()).await.unwrap_err();
+    assert!(
+        matches!(&error, Error::Other(message) if message == "Not pre-signed: /v3/routes?devid=0"),
+        "{error}"
+    );
+    let error = ptv.routes(Default::default()).await.unwrap_err();
+    assert!(
+        matches!(&error, Error::Other(message) if message == "Not pre-signed: /v3/routes?devid=0"),
+        "{error}"
+    );
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn test_tracing() {
+    /// Writer capturing everything logged
+    #[derive(Clone, Default)]
+    struct Capture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+    impl std::io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let capture = Capture::default();
+    let writer = capture.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let data = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let bytes = data.trim().len();
+    let ptv = PTV::mock(HashMap::from([
+        (
+            "/v3/departures/route_type/0/stop/1181".to_string(),
+            Ok(data),
+        ),
+        (
+            "/v3/route_types".to_string(),
+            Err(Error::HTTP(StatusCode::FORBIDDEN, "Forbidden".to_string())),
+        ),
+    ]));
+    ptv.departures(&SOUTHERN_CROSS, Default::default())
+        .await
+        .unwrap();
+    assert!(ptv.route_types().await.is_err());
+
+    let logged = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = logged.lines().collect();
+    assert_eq!(lines.len(), 2, "{logged}");
+    assert!(lines[0].contains("operation=\"Departures_GetForStop\""));
+    assert!(lines[0].contains("route_type=\"0\" stop_id=\"1181\""));
+    assert!(lines[0].contains("Request succeeded latency_ms="));
+    // The response is recorded in the span whichever HTTP client is used
+    assert!(lines[0].contains(&format!("status=200 bytes={bytes} parse_ms=")));
+    assert!(lines[1].contains("operation=\"RouteTypes_GetRouteTypes\""));
+    assert!(lines[1].contains("status=403 bytes=9}"));
+    assert!(lines[1].contains("Request failed"));
+    assert!(lines[1].contains("status=403 error="));
+    assert!(lines[1].contains("Forbidden"));
+    assert!(!lines[1].contains("parse_ms"));
+
+    // Signatures are never logged
+    assert!(lines[0].contains("&signature=REDACTED"));
+    let url = ptv.build_url("/v3/route_types", ()).await.unwrap();
+    let signature = url.query_pairs().find(|(k, _)| k == "signature").unwrap().1;
+    assert!(!logged.contains(signature.as_ref()));
+    assert_eq!(
+        redacted_url(&url),
+        "https://timetableapi.ptv.vic.gov.au/v3/route_types?devid=0&signature=REDACTED"
+    );
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let departures = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let disruptions = std::fs::read_to_string("testdata/disruptions.json").unwrap();
+    let offline =
+        r#"{"message": "Service unavailable", "status": {"version": "3.0", "health": 0}}"#;
+    let offline: ErrorResponse = parse_json(offline).unwrap();
+    let metrics = std::sync::Arc::new(PrometheusMetrics::new());
+    let stop = Stop::from_str("Stop:Train/1181").unwrap();
+    let ptv = PTV::mock(HashMap::from([
+        (
+            "/v3/departures/route_type/0/stop/1181".to_string(),
+            Ok(departures),
+        ),
+        ("/v3/disruptions".to_string(), Ok(disruptions)),
+        (
+            "/v3/route_types".to_string(),
+            Err(Error::API(StatusCode::SERVICE_UNAVAILABLE, offline)),
+        ),
+    ]))
+    .with_observer(metrics.clone());
+
+    // Domain metrics computed from responses
+    let response = ptv.departures(&stop, Default::default()).await.unwrap();
+    let delays = response.route_delays();
+    assert_eq!(delays[&RouteId::new(14)], time::Duration::seconds(60));
+    assert_eq!(delays[&RouteId::new(16)], time::Duration::seconds(90));
+    assert_eq!(delays[&RouteId::new(3)], time::Duration::ZERO);
+    assert_eq!(response.cancelled_runs(), 0);
+    metrics.record_departures(&stop, &response);
+    let disruptions = ptv.disruptions(Default::default()).await.unwrap();
+    let active = disruptions.disruptions.active_by_type();
+    assert_eq!(active, BTreeMap::from([("Planned Works".to_string(), 1)]));
+    metrics.record_disruptions(&disruptions.disruptions);
+
+    let text = metrics.render();
+    assert!(text.contains(
+        "ptv_requests_total{operation=\"Departures_GetForStop\",outcome=\"success\"} 1\n"
+    ));
+    assert!(text.contains(
+        "ptv_request_duration_seconds_count{operation=\"Disruptions_GetAllDisruptions\"} 1\n"
+    ));
+    assert!(text.contains("ptv_api_online 1\n"));
+    assert!(text.contains(
+        "ptv_route_delay_seconds{route_type=\"Train\",stop_id=\"1181\",route_id=\"16\",route_name=\"Werribee\"} 90\n"
+    ));
+    assert!(text.contains("ptv_cancelled_runs{route_type=\"Train\",stop_id=\"1181\"} 0\n"));
+    assert!(text.contains("ptv_active_disruptions{disruption_type=\"Planned Works\"} 1\n"));
+
+    // Delays are kept per stop, and routes without estimates any more are dropped
+    let other = Stop::from_str("Stop:Train/1071").unwrap();
+    metrics.record_departures(&other, &response);
+    let mut later = response.clone();
+    for departure in &mut later.departures {
+        if departure.route_id == RouteId::new(16) {
+            departure.estimated_departure_utc = None;
+        }
+    }
+    metrics.record_departures(&stop, &later);
+    let text = metrics.render();
+    assert!(!text.contains("stop_id=\"1181\",route_id=\"16\""));
+    assert!(text.contains("stop_id=\"1181\",route_id=\"14\""));
+    assert!(text.contains(
+        "ptv_route_delay_seconds{route_type=\"Train\",stop_id=\"1071\",route_id=\"16\",route_name=\"Werribee\"} 90\n"
+    ));
+
+    // The API reporting itself offline flips the health gauge
+    assert!(ptv.route_types().await.is_err());
+    let text = metrics.render();
+    assert!(text.contains("ptv_api_online 0\n"));
+    assert!(text.contains(
+        "ptv_requests_total{operation=\"RouteTypes_GetRouteTypes\",outcome=\"api_error\"} 1\n"
+    ));
+    assert!(text.contains("# TYPE ptv_request_duration_seconds histogram\n"));
+    assert!(text.contains(
+        "ptv_request_duration_seconds_bucket{operation=\"RouteTypes_GetRouteTypes\",le=\"+Inf\"} 1\n"
+    ));
+}
+
+#[tokio::test]
+async fn test_record_replay() {
+    let departures = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let route_types = std::fs::read_to_string("testdata/index/route_types.json").unwrap();
+    let offline =
+        r#"{"message": "Service unavailable", "status": {"version": "3.0", "health": 0}}"#;
+    let offline: ErrorResponse = parse_json(offline).unwrap();
+    let stop = Stop::from_str("Stop:Train/1181").unwrap();
+    let params = || DeparturesParams {
+        include_cancelled: Some(true),
+        ..Default::default()
+    };
+
+    // Record responses and errors from the upstream client
+    let client = RecordingClient::new(MockClient(HashMap::from([
+        (
+            "/v3/departures/route_type/0/stop/1181?include_cancelled=true".to_string(),
+            Ok(departures),
+        ),
+        ("/v3/route_types".to_string(), Ok(route_types)),
+        (
+            "/v3/disruptions".to_string(),
+            Err(Error::API(StatusCode::SERVICE_UNAVAILABLE, offline)),
+        ),
+        (
+            "/v3/runs/1/route_type/0".to_string(),
+            Err(Error::HTTP(StatusCode::NOT_FOUND, "Not Found".to_string())),
+        ),
+    ])));
+    let ptv = PTV::with_client(client.clone(), "1234567", "secret-key");
+    let recorded = ptv.departures(&stop, params()).await.unwrap();
+    ptv.route_types().await.unwrap();
+    assert!(ptv.disruptions(Default::default()).await.is_err());
+    let run = Run::from_str("Run:Train/1").unwrap();
+    assert!(ptv.run(&run, Default::default()).await.is_err());
+
+    // Credentials are scrubbed from the cassette
+    let path = std::env::temp_dir().join(format!("ptv-cassette-{}.json", std::process::id()));
+    client.save(&path).unwrap();
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(!saved.contains("1234567"), "{saved}");
+    assert!(!saved.contains("signature"), "{saved}");
+    let cassette = Cassette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let requests: Vec<(&str, u16)> = cassette
+        .interactions
+        .iter()
+        .map(|interaction| (interaction.request.as_str(), interaction.status))
+        .collect();
+    assert_eq!(
+        requests,
+        [
+            (
+                "/v3/departures/route_type/0/stop/1181?include_cancelled=true",
+                200
+            ),
+            ("/v3/route_types", 200),
+            ("/v3/disruptions", 503),
+            ("/v3/runs/1/route_type/0", 404),
+        ]
+    );
+
+    // Replay with different credentials, and the same results
+    let ptv = PTV::with_client(ReplayClient::new(cassette), "0", "other-key");
+    let replayed = ptv.departures(&stop, params()).await.unwrap();
+    assert_eq!(
+        serde_json::to_value(&replayed).unwrap(),
+        serde_json::to_value(&recorded).unwrap()
+    );
+    assert_eq!(ptv.route_types().await.unwrap().route_types.len(), 2);
+    match ptv.disruptions(Default::default()).await {
+        Err(Error::API(StatusCode::SERVICE_UNAVAILABLE, error)) => {
+            assert_eq!(error.status.health, HealthStatus::OFFLINE)
+        }
+        other => panic!("Expected API error, got {other:?}"),
+    }
+    assert!(matches!(
+        ptv.run(&run, Default::default()).await,
+        Err(Error::HTTP(StatusCode::NOT_FOUND, body)) if body == "Not Found"
+    ));
+
+    // Unrecorded requests fail clearly, rather than matching something else
+    match ptv.departures(&stop, Default::default()).await {
+        Err(Error::Other(message)) => assert!(
+            message.starts_with(
+                "No recorded response for /v3/departures/route_type/0/stop/1181 (recorded: "
+            ),
+            "{message}"
+        ),
+        other => panic!("Expected unrecorded error, got {other:?}"),
+    }
+
+    // Repeated requests are served in order, then the last is repeated
+    let interaction = |status: u16| Interaction {
+        request: "/v3/route_types".to_string(),
+        status,
+        response: serde_json::json!("Bad Gateway"),
+    };
+    let cassette = Cassette {
+        interactions: vec![interaction(502), interaction(500)],
+    };
+    let ptv = PTV::with_client(ReplayClient::new(cassette), "0", "0");
+    for status in [502, 500, 500] {
+        match ptv.route_types().await {
+            Err(Error::HTTP(code, _)) => assert_eq!(code.as_u16(), status),
+            other => panic!("Expected HTTP error, got {other:?}"),
+        }
+    }
+}
+
+#[cfg(all(feature = "mock-server", feature = "reqwest"))]
+#[tokio::test]
+async fn test_mock_server() {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    const KEY: &str = "00000000-0000-0000-0000-000000000000";
+    let route_types = std::fs::read_to_string("testdata/index/route_types.json").unwrap();
+    let departures = std::fs::read_to_string("testdata/departures_0_1181.json").unwrap();
+    let departures: DeparturesResponse = parse_json(&departures).unwrap();
+    let stop = Stop::from_str("Stop:Train/1181").unwrap();
+    let server = Arc::new(
+        MockServer::new("0", KEY)
+            .with_fixture("/v3/route_types", route_types)
+            .with_departures(
+                &stop,
+                departures,
+                time::macros::datetime!(2023-07-20 09:30 UTC),
+            ),
+    );
+    let base = server.spawn().await.unwrap();
+    let ptv = PTV::new("0", KEY, "RustPTVTest/0.1").with_base_url(base.clone());
+
+    // Fixtures are served to correctly signed requests
+    let response = ptv.route_types().await.unwrap();
+    assert_eq!(response.route_types.len(), 2);
+    assert_eq!(response.status.health, HealthStatus::ONLINE);
+
+    // Departures are relative to now, the first was 5 minutes after recording
+    let params = DeparturesParams {
+        include_cancelled: Some(true),
+        ..Default::default()
+    };
+    let response = ptv.departures(&stop, params).await.unwrap();
+    let until = response.departures[0].scheduled_departure_utc - OffsetDateTime::now_utc();
+    assert!(
+        (time::Duration::minutes(4)..=time::Duration::minutes(5)).contains(&until),
+        "{until}"
+    );
+    let delay = response.resolved().next().unwrap().delay().unwrap();
+    assert_eq!(delay, time::Duration::minutes(1));
+
+    // Credentials are checked
+    let forbidden = |result: Result<RouteTypesResponse>| match result {
+        Err(Error::API(StatusCode::FORBIDDEN, error)) => error.message,
+        other => panic!("Expected forbidden, got {other:?}"),
+    };
+    let wrong_key = PTV::new("0", "wrong", "RustPTVTest/0.1").with_base_url(base.clone());
+    assert_eq!(
+        forbidden(wrong_key.route_types().await),
+        "Signature doesn't match"
+    );
+    let wrong_devid = PTV::new("1", KEY, "RustPTVTest/0.1").with_base_url(base.clone());
+    assert_eq!(forbidden(wrong_devid.route_types().await), "Invalid devid");
+
+    // Missing fixtures, and injected faults
+    let status = |result: Result<DisruptionsResponse>| match result {
+        Err(Error::API(status, _)) => status,
+        other => panic!("Expected API error, got {other:?}"),
+    };
+    assert_eq!(
+        status(ptv.disruptions(Default::default()).await),
+        StatusCode::NOT_FOUND
+    );
+    server.set_fault("/v3/route_types", Some(StatusCode::TOO_MANY_REQUESTS));
+    assert!(matches!(
+        ptv.route_types().await,
+        Err(Error::API(StatusCode::TOO_MANY_REQUESTS, _))
+    ));
+    server.set_fault("/v3/", Some(StatusCode::INTERNAL_SERVER_ERROR));
+    assert_eq!(
+        status(ptv.disruptions(Default::default()).await),
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+    server.set_fault("/v3/", None);
+    server.set_fault("/v3/route_types", None);
+    ptv.route_types().await.unwrap();
+
+    // Health and latency
+    server.set_health(HealthStatus::OFFLINE);
+    server.set_latency(Duration::from_millis(100));
+    let start = Instant::now();
+    let response = ptv.route_types().await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert_eq!(response.status.health, HealthStatus::OFFLINE);
+}
@@ -91,6 +91,12 @@ id_type!(
     /// Disruption Identifier
     DisruptionId(i32)
 );
+id_type!(
+    /// GTFS Stop Identifier (i.e. `stop_id` in a GTFS `stops.txt`)
+    ///
+    /// See [GtfsStopMap] to map to and from PTV stop identifiers
+    GtfsStopId(String)
+);
 
 /// All route types (i.e. identifiers of transport modes) and their names.
 ///
@@ -138,10 +144,104 @@ impl FromStr for RouteType {
     }
 }
 
+/// Serde helpers for API date/time values
+///
+/// PTV sends ISO 8601 UTC date/times (i.e. `2023-07-20T09:41:00Z`), but `time::serde::iso8601`
+/// serializes with a six digit year, so they're written back out in the same RFC 3339 form PTV uses.
+pub(crate) mod datetime {
+    use serde::{Deserializer, Serializer};
+    use time::format_description::well_known::Rfc3339;
+    use time::OffsetDateTime;
+
+    pub fn serialize<S: Serializer>(
+        datetime: &OffsetDateTime,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let text = datetime
+            .format(&Rfc3339)
+            .map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<OffsetDateTime, D::Error> {
+        time::serde::iso8601::deserialize(deserializer)
+    }
+
+    /// Format as used in request parameters (i.e. `date_utc`)
+    pub fn to_string(datetime: OffsetDateTime) -> String {
+        datetime
+            .to_offset(time::UtcOffset::UTC)
+            .format(&Rfc3339)
+            .expect("Failed to format date")
+    }
+
+    /// Convert to Melbourne local time (AEST/AEDT)
+    ///
+    /// The API only deals in UTC, and `time` has no timezone database, so this hardcodes the
+    /// Victorian daylight saving rules in force since 2008.
+    pub fn to_melbourne(datetime: OffsetDateTime) -> OffsetDateTime {
+        use time::{Date, Month, UtcOffset};
+        let year = datetime.to_offset(UtcOffset::UTC).year();
+        let first_sunday = |month: Month| {
+            let first = Date::from_calendar_date(year, month, 1).unwrap();
+            first + time::Duration::days((7 - first.weekday().number_days_from_sunday() as i64) % 7)
+        };
+        // Daylight saving starts at 2am AEST in October and ends at 3am AEDT in April,
+        // which are both 16:00 UTC on the Saturday before
+        let change = |month: Month| {
+            let saturday = first_sunday(month).previous_day().unwrap();
+            saturday.with_hms(16, 0, 0).unwrap().assume_utc()
+        };
+        let daylight_saving = datetime >= change(Month::October) || datetime < change(Month::April);
+        let hours = if daylight_saving { 11 } else { 10 };
+        datetime.to_offset(UtcOffset::from_hms(hours, 0, 0).unwrap())
+    }
+
+    /// Start of a day in Melbourne
+    pub fn melbourne_midnight(date: time::Date) -> OffsetDateTime {
+        let standard = date
+            .midnight()
+            .assume_offset(time::UtcOffset::from_hms(10, 0, 0).unwrap());
+        date.midnight()
+            .assume_offset(to_melbourne(standard).offset())
+    }
+
+    pub mod option {
+        use super::*;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(transparent)]
+        struct Wrapper(#[serde(with = "super")] OffsetDateTime);
+
+        pub fn serialize<S: Serializer>(
+            datetime: &Option<OffsetDateTime>,
+            serializer: S,
+        ) -> std::result::Result<S::Ok, S::Error> {
+            datetime.map(Wrapper).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Option<OffsetDateTime>, D::Error> {
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(datetime)| datetime))
+        }
+    }
+}
+
+/// Convert a date/time to Melbourne local time (AEST/AEDT), for display
+///
+/// Times from the API are all UTC.
+pub fn melbourne_time(datetime: OffsetDateTime) -> OffsetDateTime {
+    datetime::to_melbourne(datetime)
+}
+
 /// API system health status
 ///
 /// Swagger type: `int32`
-#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
 pub struct HealthStatus(i32);
 impl HealthStatus {
     pub const OFFLINE: HealthStatus = HealthStatus(0);
@@ -160,29 +260,35 @@ impl std::fmt::Display for HealthStatus {
 /// API Status / Metadata
 ///
 /// Swagger type: `V3.Status`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Status {
     /// API Version number
     pub version: String,
     /// API system health status
     pub health: HealthStatus,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// An error response
 ///
 /// Swagger type: `V3.ErrorResponse`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ErrorResponse {
     /// Error message
     pub message: String,
     /// API Status / Metadata
     pub status: Status,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Service departure details
 ///
 /// Swagger type: `V3.Departure`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DepartureDetails {
     /// Stop identifier
     pub stop_id: StopId,
@@ -198,10 +304,10 @@ pub struct DepartureDetails {
     /// Disruption information identifier(s)
     pub disruption_ids: Vec<DisruptionId>,
     /// Scheduled (i.e. timetabled) departure time and date in ISO 8601 UTC format
-    #[serde(with = "time::serde::iso8601")]
+    #[serde(with = "datetime")]
     pub scheduled_departure_utc: OffsetDateTime,
     /// Real-time estimate of departure time and date in ISO 8601 UTC format
-    #[serde(with = "time::serde::iso8601::option")]
+    #[serde(with = "datetime::option")]
     pub estimated_departure_utc: Option<OffsetDateTime>,
     /// Indicates if the metropolitan train service is at the platform at the time of query; returns false for other modes
     pub at_platform: bool,
@@ -212,14 +318,18 @@ pub struct DepartureDetails {
     /// Chronological sequence for the departures in a run. Order ascendingly by this field to get chronological order (earliest first) of departures with the same run_ref.
     /// NOTE, this field is not always N+1 or N-1 of the previous or following departure. e.g 100, 200, 250, 300 instead of 1, 2, 3, 4
     pub departure_sequence: i32,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// A train station, tram stop, bus stop, regional coach stop or Night Bus stop
 ///
 /// Swagger type: `V3.StopModel` + `V3.ResultStop`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct StopDetails {
     /// Distance of stop from input location (in metres); returns 0 if no location is input
+    #[serde(default)]
     pub stop_distance: f32,
     /// suburb of stop
     pub stop_suburb: String,
@@ -230,15 +340,20 @@ pub struct StopDetails {
     /// Transport mode identifier
     pub route_type: RouteType,
     /// Geographic coordinate of latitude at stop
-    pub stop_latitude: f32,
+    pub stop_latitude: f64,
     /// Geographic coordinate of longitude at stop
-    pub stop_longitude: f32,
+    pub stop_longitude: f64,
     /// Landmark in proximity of stop
     pub stop_landmark: String,
     /// Sequence of the stop on the route/run; return 0 when route_id or run_id not specified. Order ascendingly by this field (when non zero) to get physical order (earliest first) of stops on the route_id/run_id.
     pub stop_sequence: i32,
     /// List of routes travelling through the stop
     pub routes: Option<Vec<RouteDetails>>,
+    /// Disruption information identifier(s) (only returned by geolocation searches)
+    pub disruption_ids: Option<Vec<DisruptionId>>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 impl StopDetails {
     pub fn stop(&self) -> Stop {
@@ -247,12 +362,16 @@ impl StopDetails {
             id: self.stop_id.clone(),
         }
     }
+    /// Geographic coordinate of the stop
+    pub fn location(&self) -> LatLon {
+        LatLon::new(self.stop_latitude, self.stop_longitude)
+    }
 }
 
 /// Descriptor of the trip/service run
 ///
 /// Swagger type: `V3.VehicleDescriptor`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct VehicleDescriptor {
     /// Operator name of the vehicle such as:
     ///   "Metro Trains Melbourne"
@@ -289,12 +408,15 @@ pub struct VehicleDescriptor {
     /// The length of the vehicle.
     /// Applies to CIS - Metro Trains
     pub length: Option<String>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Position of the trip/service run
 ///
 /// Swagger type: `V3.VehiclePosition`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct VehiclePosition {
     /// Geographic coordinate of latitude of the vehicle when known.
     /// Only available for some bus runs.
@@ -319,12 +441,22 @@ pub struct VehiclePosition {
     pub datetime_utc: String,
     /// CIS - Metro Train Vehicle Location data expiry time
     pub expiry_time: Option<String>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl VehiclePosition {
+    /// Geographic coordinate of the vehicle when known
+    pub fn location(&self) -> Option<LatLon> {
+        Some(LatLon::new(self.latitude?, self.longitude?))
+    }
 }
 
 /// Individual trips/services of a route
 ///
 /// Swagger type: `V3.Run`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RunDetails {
     /// Numeric trip/service run identifier. Defaults to -1 when run identifier is Alphanumeric
     pub run_id: RunId,
@@ -354,12 +486,15 @@ pub struct RunDetails {
     /// Only available for some runs.
     /// May be null.
     pub vehicle_descriptor: Option<VehicleDescriptor>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Directions of travel of route
 ///
 /// Swagger type: `V3.Direction`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DirectionDetails {
     /// Direction of travel identifier
     pub direction_id: DirectionId,
@@ -369,23 +504,29 @@ pub struct DirectionDetails {
     pub route_id: RouteId,
     /// Transport mode identifier
     pub route_type: RouteType,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Train lines, tram routes, bus routes, regional coach routes, Night Bus routes
 ///
 /// Swagger type: `V3.RouteServiceStatus`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ServiceStatusDetails {
     /// Service status description
     pub description: String,
     /// Time of status
     pub timestamp: String,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Train lines, tram routes, bus routes, regional coach routes, Night Bus routes
 ///
 /// Swagger type: `V3.ResultRoute`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RouteDetails {
     /// Transport mode identifier
     pub route_type: RouteType,
@@ -399,12 +540,43 @@ pub struct RouteDetails {
     pub route_gtfs_id: Option<String>,
     /// Service status for the route (indicates disruptions)
     pub route_service_status: Option<ServiceStatusDetails>,
+    /// Geographic paths of the route, by direction (requires `include_geopath`)
+    pub geopath: Option<Vec<GeoPath>>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+impl RouteDetails {
+    pub fn route(&self) -> Route {
+        Route {
+            route_type: self.route_type,
+            id: self.route_id.clone(),
+        }
+    }
+}
+
+/// Geographic path of a route in one direction of travel
+///
+/// Swagger type: `V3.GeoPath`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GeoPath {
+    /// Direction of travel identifier
+    pub direction_id: DirectionId,
+    /// Date the path is valid from (i.e. `2020-12-09`)
+    pub valid_from: String,
+    /// Date the path is valid until (i.e. `2050-12-31`)
+    pub valid_to: String,
+    /// Coordinates making up the path, the route may be made up of several disconnected paths
+    pub paths: Vec<Polyline>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Route relevant to a disruption (if applicable)
 ///
 /// Swagger type: `V3.DisruptionRoute`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DisruptionRoute {
     /// Transport mode identifier
     pub route_type: RouteType,
@@ -418,12 +590,15 @@ pub struct DisruptionRoute {
     pub route_gtfs_id: Option<String>,
     /// Direction of travel relevant to a disruption (if applicable)
     pub route_service_status: Option<DisruptionDirection>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Direction of travel relevant to a disruption
 ///
 /// Swagger type: `V3.DisruptionDirection`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DisruptionDirection {
     /// Route and direction of travel combination identifier
     pub route_direction_id: i32,
@@ -434,23 +609,29 @@ pub struct DisruptionDirection {
     /// Time of service to which disruption applies, in 24 hour clock format (HH:MM:SS) AEDT/AEST;
     /// returns null if disruption applies to multiple (or no) services
     pub service_time: String,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 ///  Stop relevant to a disruption (if applicable)
 ///
 /// Swagger type: `V3.DisruptionStop`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DisruptionStop {
     /// Stop identifier
     pub stop_id: StopId,
     /// Name of stop
     pub stop_name: String,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// Disruption information applicable to relevant routes or stops
 ///
 /// Swagger type: `V3.Disruption`
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DisruptionDetails {
     /// Disruption information identifier
     pub disruption_id: DisruptionId,
@@ -480,13 +661,57 @@ pub struct DisruptionDetails {
     pub routes: Option<Vec<DisruptionRoute>>,
     /// Stop relevant to a disruption (if applicable)
     pub stops: Option<Vec<DisruptionStop>>,
+    /// Colour used when displaying the disruption (i.e. `#ffd500`)
+    ///
+    /// Not documented in the Swagger spec
     pub colour: Option<String>,
+    /// Not documented in the Swagger spec
     pub display_on_board: Option<bool>,
+    /// Not documented in the Swagger spec
     pub display_status: Option<bool>,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// A transport mode
+///
+/// Swagger type: `V3.RouteType`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RouteTypeDetails {
+    /// Name of transport mode
+    pub route_type_name: String,
+    /// Transport mode identifier
+    pub route_type: RouteType,
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// TODO
 ///
 /// Swagger type: `V3.ResultOutlet`
-#[derive(Clone, Debug, Deserialize)]
-pub struct OutletDetails {}
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OutletDetails {
+    /// Fields returned by the API which aren't modelled by this crate
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+unknown_fields!(Status);
+unknown_fields!(ErrorResponse, status);
+unknown_fields!(DepartureDetails);
+unknown_fields!(StopDetails, routes);
+unknown_fields!(VehicleDescriptor);
+unknown_fields!(VehiclePosition);
+unknown_fields!(RunDetails, vehicle_position, vehicle_descriptor);
+unknown_fields!(DirectionDetails);
+unknown_fields!(ServiceStatusDetails);
+unknown_fields!(RouteDetails, route_service_status, geopath);
+unknown_fields!(GeoPath);
+unknown_fields!(DisruptionRoute, route_service_status);
+unknown_fields!(DisruptionDirection);
+unknown_fields!(DisruptionStop);
+unknown_fields!(DisruptionDetails, routes, stops);
+unknown_fields!(RouteTypeDetails);
+unknown_fields!(OutletDetails);
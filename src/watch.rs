@@ -0,0 +1,89 @@
+use crate::*;
+use futures_util::stream::{self, Stream};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A change to a departure between two polls of [PTV::watch_departures]
+#[derive(Clone, Debug)]
+pub enum DepartureEvent {
+    /// A departure which is no longer listed because it has left
+    Departed(DepartureDetails),
+    /// Any other change, including departures which vanished before they were due to leave
+    Changed(DepartureChange),
+}
+
+/// An item from the [PTV::watch_departures] stream
+#[derive(Clone, Debug)]
+pub struct DeparturesUpdate {
+    /// The departures returned by this poll
+    pub snapshot: Arc<DeparturesResponse>,
+    /// Changes since the previous successful poll (empty for the first)
+    pub events: Vec<DepartureEvent>,
+}
+
+/// Internal helper to find the changes between two polls, telling departed services apart
+fn departure_events(
+    previous: &DeparturesResponse,
+    current: &DeparturesResponse,
+    now: OffsetDateTime,
+) -> Vec<DepartureEvent> {
+    diff(previous, current)
+        .into_iter()
+        .map(|change| match change {
+            DepartureChange::Vanished(departure)
+                if departure
+                    .estimated_departure_utc
+                    .unwrap_or(departure.scheduled_departure_utc)
+                    <= now =>
+            {
+                DepartureEvent::Departed(departure)
+            }
+            change => DepartureEvent::Changed(change),
+        })
+        .collect()
+}
+
+impl<Client: PTVHttpClient> PTV<Client> {
+    /// Poll departures for a stop, streaming each snapshot along with what changed since the last one
+    ///
+    /// The first poll happens immediately, then every `interval`. Errors are yielded as they
+    /// happen and polling continues, changes are always relative to the last successful poll.
+    /// Leave `date_utc` unset so each poll fetches the current departures.
+    ///
+    /// Requires the `watch` feature, which uses `tokio` for the interval.
+    pub fn watch_departures<'a>(
+        &'a self,
+        stop: &Stop,
+        params: DeparturesParams,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<DeparturesUpdate>> + 'a {
+        let stop = stop.clone();
+        let state: (Option<Arc<DeparturesResponse>>, bool) = (None, true);
+        stream::unfold(state, move |(previous, first)| {
+            let stop = stop.clone();
+            let params = params.clone();
+            async move {
+                if !first {
+                    tokio::time::sleep(interval).await;
+                }
+                match self.departures(&stop, params).await {
+                    Ok(snapshot) => {
+                        let snapshot = Arc::new(snapshot);
+                        let events = match &previous {
+                            Some(previous) => {
+                                departure_events(previous, &snapshot, OffsetDateTime::now_utc())
+                            }
+                            None => Vec::new(),
+                        };
+                        let update = DeparturesUpdate {
+                            snapshot: snapshot.clone(),
+                            events,
+                        };
+                        Some((Ok(update), (Some(snapshot), false)))
+                    }
+                    Err(error) => Some((Err(error), (previous, false))),
+                }
+            }
+        })
+    }
+}